@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use log::Levels;
+
+/// Command line usage, printed when arguments can not be parsed
+pub const USAGE: &'static str = "Usage: hungry-pixel-rs [--seed <number>] [--record <file> | \
+                                  --replay <file>] [--terminal] [--software-renderer] \
+                                  [--screenshot-dir <dir>] [--screenshot-frame <number>] \
+                                  [--capture-every <number>] [--log-level <levels>] \
+                                  [--log-file <file>]";
+
+/// Options passed to the game on the command line
+#[derive(Debug, Default)]
+pub struct CliOptions {
+    /// Seed for the spawn RNG, so that a run can be reproduced
+    pub seed: Option<u32>,
+    /// Replay file to record the session to
+    pub record: Option<PathBuf>,
+    /// Replay file to play back instead of reading the keyboard
+    pub replay: Option<PathBuf>,
+    /// Play in the terminal instead of a window
+    pub terminal: bool,
+    /// Use SDL's software renderer (needed e.g. with the dummy video driver)
+    pub software_renderer: bool,
+    /// Directory for screenshots and frame dumps
+    pub screenshot_dir: Option<PathBuf>,
+    /// Take a screenshot of this frame
+    pub screenshot_frame: Option<u32>,
+    /// Save every Nth frame
+    pub capture_every: Option<u32>,
+    /// Most verbose levels that are logged, e.g. `debug` or `warn,input=trace`; overrides
+    /// the environment
+    pub log_level: Option<Levels>,
+    /// Where the log is written instead of the default location
+    pub log_file: Option<PathBuf>,
+}
+
+impl CliOptions {
+    /// Parses arguments (without the executable name)
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<CliOptions, String> {
+        let mut options = CliOptions::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => options.seed = Some(parse_number("--seed", args.next())?),
+                "--record" => {
+                    let value = args.next().ok_or_else(|| "--record requires a file".to_string())?;
+                    options.record = Some(PathBuf::from(value));
+                }
+                "--replay" => {
+                    let value = args.next().ok_or_else(|| "--replay requires a file".to_string())?;
+                    options.replay = Some(PathBuf::from(value));
+                }
+                "--terminal" => options.terminal = true,
+                "--software-renderer" => options.software_renderer = true,
+                "--screenshot-dir" => {
+                    let value = args.next()
+                        .ok_or_else(|| "--screenshot-dir requires a directory".to_string())?;
+                    options.screenshot_dir = Some(PathBuf::from(value));
+                }
+                "--screenshot-frame" => {
+                    options.screenshot_frame = Some(parse_number("--screenshot-frame",
+                                                                 args.next())?);
+                }
+                "--capture-every" => {
+                    options.capture_every = Some(parse_number("--capture-every", args.next())?);
+                }
+                "--log-level" => {
+                    let value = args.next()
+                        .ok_or_else(|| "--log-level requires a level".to_string())?;
+                    options.log_level = Some(value.parse()?);
+                }
+                "--log-file" => {
+                    let value = args.next().ok_or_else(|| "--log-file requires a file".to_string())?;
+                    options.log_file = Some(PathBuf::from(value));
+                }
+                x => return Err(format!("Unknown argument: {}", x)),
+            }
+        }
+        if options.record.is_some() && options.replay.is_some() {
+            return Err("--record and --replay can not be used together".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number(name: &str, value: Option<String>) -> Result<u32, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", name))?;
+    value.parse::<u32>().map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use sdl2::Sdl;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+
+use log::Category;
+
+/// Sample rate sounds are made at, they are stretched if the device runs at another one
+pub const SAMPLE_RATE: i32 = 22050;
+/// Loudest volume step
+pub const MAX_VOLUME: u8 = 10;
+/// Most sounds played at the same time, the oldest one is cut off to make room
+const MAX_VOICES: usize = 8;
+
+/// Mono samples between -1.0 and 1.0 at `SAMPLE_RATE`, shared with the audio thread
+pub type Samples = Arc<Vec<f32>>;
+
+/// Changes to the volume, applied by the engine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioCommand {
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+}
+
+/// Volume settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioSettings {
+    /// From 0 to `MAX_VOLUME`
+    pub volume: u8,
+    /// Muting keeps the volume, so unmuting restores it
+    pub muted: bool,
+}
+
+impl AudioSettings {
+    pub fn new() -> AudioSettings {
+        AudioSettings {
+            volume: 7,
+            muted: false,
+        }
+    }
+
+    pub fn apply(&mut self, command: AudioCommand) {
+        match command {
+            AudioCommand::VolumeUp if self.volume < MAX_VOLUME => self.volume += 1,
+            AudioCommand::VolumeDown if self.volume > 0 => self.volume -= 1,
+            AudioCommand::ToggleMute => self.muted = !self.muted,
+            _ => {}
+        }
+    }
+
+    /// Factor the samples are multiplied with
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume as f32 / MAX_VOLUME as f32
+        }
+    }
+}
+
+/// A sound being played
+struct Voice {
+    samples: Samples,
+    /// Index of the next sample, fractional when the device rate differs from `SAMPLE_RATE`
+    position: f32,
+}
+
+/// Mixes the voices on SDL's audio thread
+struct Mixer {
+    voices: Vec<Voice>,
+    gain: f32,
+    /// How far a voice advances per output sample
+    step: f32,
+}
+
+impl AudioCallback for Mixer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = 0.0;
+        }
+        let gain = self.gain;
+        let step = self.step;
+        for voice in &mut self.voices {
+            for sample in out.iter_mut() {
+                let index = voice.position as usize;
+                if index >= voice.samples.len() {
+                    break;
+                }
+                *sample += voice.samples[index] * gain;
+                voice.position += step;
+            }
+        }
+        self.voices.retain(|voice| (voice.position as usize) < voice.samples.len());
+        for sample in out.iter_mut() {
+            *sample = sample.max(-1.0).min(1.0);
+        }
+    }
+}
+
+/// Sound output
+/// Plays sample buffers through SDL's audio subsystem. Without a usable device (no sound
+/// card, or no audio driver at all) the game just stays silent; SDL's dummy driver
+/// (`SDL_AUDIODRIVER=dummy`) opens fine and consumes the sounds without playing them.
+pub struct Audio {
+    device: Option<AudioDevice<Mixer>>,
+}
+
+impl Audio {
+    /// Opens the default playback device
+    pub fn new(sdl: &Sdl, settings: AudioSettings) -> Audio {
+        match open_device(sdl, settings.gain()) {
+            Ok(device) => {
+                info!(Category::Engine, "Audio opened");
+                Audio { device: Some(device) }
+            }
+            Err(e) => {
+                warn!(Category::Engine, "Could not open audio, playing without sound: {}", e);
+                Audio::silent()
+            }
+        }
+    }
+
+    /// Audio that plays nothing
+    pub fn silent() -> Audio {
+        Audio { device: None }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.device.is_some()
+    }
+
+    /// Starts playing the samples on top of whatever is playing
+    pub fn play(&mut self, samples: &Samples) {
+        if let Some(ref mut device) = self.device {
+            let mut mixer = device.lock();
+            if mixer.voices.len() >= MAX_VOICES {
+                mixer.voices.remove(0);
+            }
+            mixer.voices.push(Voice {
+                samples: samples.clone(),
+                position: 0.0,
+            });
+        }
+    }
+
+    /// Applies the volume to everything playing and played from now on
+    pub fn set_settings(&mut self, settings: AudioSettings) {
+        if let Some(ref mut device) = self.device {
+            device.lock().gain = settings.gain();
+        }
+    }
+}
+
+fn open_device(sdl: &Sdl, gain: f32) -> Result<AudioDevice<Mixer>, String> {
+    let audio = sdl.audio()?;
+    let desired = AudioSpecDesired {
+        freq: Some(SAMPLE_RATE),
+        channels: Some(1),
+        samples: None,
+    };
+    let device = audio.open_playback(None, &desired, |spec| {
+            Mixer {
+                voices: Vec::new(),
+                gain: gain,
+                step: SAMPLE_RATE as f32 / spec.freq as f32,
+            }
+        })?;
+    device.resume();
+    Ok(device)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::sync::Arc;
+
+    use sdl2;
+    use sdl2::audio::AudioCallback;
+
+    use super::*;
+
+    #[test]
+    fn volume_stays_in_range() {
+        let mut settings = AudioSettings {
+            volume: 0,
+            muted: false,
+        };
+        settings.apply(AudioCommand::VolumeDown);
+        assert_eq!(settings.volume, 0);
+        assert_eq!(settings.gain(), 0.0);
+        settings.volume = MAX_VOLUME;
+        settings.apply(AudioCommand::VolumeUp);
+        assert_eq!(settings.volume, MAX_VOLUME);
+        assert_eq!(settings.gain(), 1.0);
+        settings.apply(AudioCommand::ToggleMute);
+        assert_eq!(settings.gain(), 0.0);
+        settings.apply(AudioCommand::ToggleMute);
+        assert_eq!(settings.volume, MAX_VOLUME);
+    }
+
+    #[test]
+    fn mixer_adds_voices_until_they_end() {
+        let mut mixer = Mixer {
+            voices: Vec::new(),
+            gain: 0.5,
+            step: 1.0,
+        };
+        for samples in vec![vec![0.5, 0.5], vec![1.0, 1.0, 1.0, 1.0]] {
+            mixer.voices.push(Voice {
+                samples: Arc::new(samples),
+                position: 0.0,
+            });
+        }
+        let mut out = [1.0; 3];
+        mixer.callback(&mut out);
+        assert_eq!(out, [0.75, 0.75, 0.5]);
+        assert_eq!(mixer.voices.len(), 1);
+        mixer.callback(&mut out);
+        assert_eq!(out, [0.5, 0.0, 0.0]);
+        assert!(mixer.voices.is_empty());
+    }
+
+    #[test]
+    fn sounds_play_on_the_dummy_driver() {
+        env::set_var("SDL_AUDIODRIVER", "dummy");
+        let sdl = sdl2::init().unwrap();
+        let mut audio = Audio::new(&sdl, AudioSettings::new());
+        assert!(audio.is_open());
+        audio.play(&Arc::new(vec![0.25; SAMPLE_RATE as usize / 10]));
+        audio.set_settings(AudioSettings {
+            volume: 3,
+            muted: true,
+        });
+    }
+}
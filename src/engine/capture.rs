@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sdl2::image::SaveSurface;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+
+use engine::error::EngineError;
+use engine::render::Frame;
+
+/// Screenshot and frame sequence capture
+/// Decides which rendered frames get saved and where to.
+pub struct Capture {
+    /// Directory screenshots are saved to, created on first use
+    pub directory: PathBuf,
+    /// Frame number to take a single screenshot of
+    pub at_frame: Option<u32>,
+    /// Save every Nth frame as a numbered image
+    pub every: Option<u32>,
+    requested: bool,
+    frame: u32,
+}
+
+impl Capture {
+    pub fn new() -> Capture {
+        Capture {
+            directory: PathBuf::from("screenshots"),
+            at_frame: None,
+            every: None,
+            requested: false,
+            frame: 0,
+        }
+    }
+
+    /// Saves the next rendered frame
+    pub fn request_screenshot(&mut self) {
+        self.requested = true;
+    }
+
+    /// Called once for every rendered frame; returns the path the frame should be saved to
+    pub fn next_frame(&mut self) -> Option<PathBuf> {
+        self.frame += 1;
+        if self.requested || self.at_frame == Some(self.frame) {
+            self.requested = false;
+            Some(self.directory.join(format!("screenshot-{:06}.png", self.frame)))
+        } else {
+            match self.every {
+                Some(n) if n > 0 && self.frame % n == 0 => {
+                    Some(self.directory.join(format!("frame-{:06}.png", self.frame)))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Writes the frame to `path` as a PNG image, creating missing directories
+pub fn save_png(frame: &Frame, path: &Path) -> Result<(), EngineError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut pixels = frame.pixels.clone();
+    let surface = Surface::from_data(&mut pixels,
+                                     frame.width,
+                                     frame.height,
+                                     frame.width * 3,
+                                     PixelFormatEnum::RGB24)
+        .map_err(EngineError::Render)?;
+    surface.save(path).map_err(EngineError::Render)
+}
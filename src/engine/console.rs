@@ -0,0 +1,132 @@
+use std::cmp;
+
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color::RGB;
+use sdl2::rect::Rect;
+
+use engine::error::OrLog;
+use engine::font::RenderableText;
+use engine::render::RenderBackend;
+
+/// How many lines of output are kept
+const HISTORY_LENGTH: usize = 100;
+
+/// Drop-down developer console
+/// Collects typed text while it is open; entered lines are returned by `key` for the engine
+/// to execute, and anything printed with `print` is shown above the prompt.
+pub struct Console {
+    pub open: bool,
+    input: String,
+    /// Entered lines and their output, oldest first
+    output: Vec<String>,
+    /// Previously entered lines, recalled with Up and Down
+    entered: Vec<String>,
+    recalled: Option<usize>,
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            open: false,
+            input: String::new(),
+            output: Vec::new(),
+            entered: Vec::new(),
+            recalled: None,
+        }
+    }
+
+    /// Opens or closes the console, returns whether it is open now
+    pub fn toggle(&mut self) -> bool {
+        self.open = !self.open;
+        self.open
+    }
+
+    /// Appends typed text to the input line
+    pub fn type_text(&mut self, text: &str) {
+        // the key opening the console must not end up in it
+        self.input.extend(text.chars().filter(|c| *c != '`'));
+    }
+
+    /// Handles a key press, returns the line to execute when Return is pressed
+    pub fn key(&mut self, k: Keycode) -> Option<String> {
+        match k {
+            Keycode::Return => {
+                let line = self.input.trim().to_string();
+                self.input.clear();
+                self.recalled = None;
+                if line.is_empty() {
+                    return None;
+                }
+                self.print(format!("> {}", line));
+                self.entered.push(line.clone());
+                Some(line)
+            }
+            Keycode::Backspace => {
+                self.input.pop();
+                None
+            }
+            Keycode::Up => {
+                let index = match self.recalled {
+                    Some(index) => index.saturating_sub(1),
+                    None => self.entered.len().saturating_sub(1),
+                };
+                self.recall(index);
+                None
+            }
+            Keycode::Down => {
+                if let Some(index) = self.recalled {
+                    self.recall(index + 1);
+                }
+                None
+            }
+            Keycode::Escape => {
+                self.open = false;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn print<T: Into<String>>(&mut self, line: T) {
+        self.output.push(line.into());
+        if self.output.len() > HISTORY_LENGTH {
+            self.output.remove(0);
+        }
+    }
+
+    fn recall(&mut self, index: usize) {
+        match self.entered.get(index) {
+            Some(line) => {
+                self.input = line.clone();
+                self.recalled = Some(index);
+            }
+            None => {
+                self.input.clear();
+                self.recalled = None;
+            }
+        }
+    }
+
+    /// Draws the console over the upper part of the drawing area
+    pub fn render(&self, r: &mut RenderBackend, window_size: (u32, u32)) {
+        let height = window_size.1 * 2 / 5;
+        r.fill_rect(Rect::new(0, 0, window_size.0, height), RGB(20, 20, 40)).or_log();
+        r.draw_rect(Rect::new(0, 0, window_size.0, height), RGB(150, 150, 150)).or_log();
+
+        let prompt = RenderableText::with_color(format!("> {}_", self.input),
+                                                (255, 255, 255, 255));
+        let line_height = r.text_size("default", prompt.text())
+            .or_log()
+            .map_or(14, |size| size.1) + 2;
+        let mut y = height as i32 - line_height as i32 - 5;
+        r.draw_text("default", &prompt, 5, y).or_log();
+
+        let visible = cmp::min(self.output.len(),
+                               ((height / line_height) as usize).saturating_sub(1));
+        for line in self.output.iter().rev().take(visible) {
+            y -= line_height as i32;
+            let text = RenderableText::with_color(line.as_str(), (180, 180, 180, 255));
+            r.draw_text("default", &text, 5, y).or_log();
+        }
+    }
+}
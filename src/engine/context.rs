@@ -2,6 +2,8 @@ use sdl2::Sdl;
 
 use sdl2;
 
+use engine::error::EngineError;
+
 /// SDL2 context
 /// Holds all SDL2 context objects and is passed by reference to ensure proper lifetimes
 pub struct SDL2Context {
@@ -9,8 +11,8 @@ pub struct SDL2Context {
 }
 
 impl SDL2Context {
-    pub fn new() -> SDL2Context {
-        let sdl_context: Sdl = sdl2::init().expect("Could not initialize SDL!");
-        SDL2Context { sdl2: sdl_context }
+    pub fn new() -> Result<SDL2Context, EngineError> {
+        let sdl_context: Sdl = sdl2::init().map_err(EngineError::Init)?;
+        Ok(SDL2Context { sdl2: sdl_context })
     }
 }
@@ -0,0 +1,201 @@
+use sdl2::event::Event;
+
+use engine::input::Input;
+
+/// How far a stick or trigger has to be pushed to count as pressed, out of 32767
+pub const DEAD_ZONE: i16 = 10000;
+
+/// What a game controller event means to the engine
+#[derive(Debug, Clone, Copy)]
+pub enum ControllerEvent {
+    /// A controller was plugged in, with its device index; it has to be opened to be used
+    Added(u32),
+    /// The controller with the instance id was unplugged
+    Removed(i32),
+    /// The input was pressed, the states get an `EngineEvent::ControllerPressed` for it
+    Pressed(Input),
+    Released(Input),
+}
+
+/// Game controller event translation
+/// Turns SDL game controller events into `ControllerEvent`s without touching any device, so
+/// it can be fed synthetic events. Sticks and triggers become buttons: pushing one past
+/// `DEAD_ZONE` presses an `Input::Axis`, letting it go back releases it. Whatever an
+/// unplugged controller was holding is released.
+pub struct ControllerTranslator {
+    /// Inputs being held, along with the instance id of their controller
+    held: Vec<(i32, Input)>,
+}
+
+impl ControllerTranslator {
+    pub fn new() -> ControllerTranslator {
+        ControllerTranslator { held: Vec::new() }
+    }
+
+    /// What `event` means, nothing if it is not a game controller event
+    pub fn translate(&mut self, event: &Event) -> Vec<ControllerEvent> {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                vec![ControllerEvent::Added(which as u32)]
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                let mut events = Vec::new();
+                for &(id, input) in &self.held {
+                    if id == which {
+                        events.push(ControllerEvent::Released(input));
+                    }
+                }
+                self.held.retain(|&(id, _)| id != which);
+                events.push(ControllerEvent::Removed(which));
+                events
+            }
+            Event::ControllerButtonDown { which, button, .. } => {
+                self.press(which, Input::Button(button))
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                self.release(which, Input::Button(button))
+            }
+            Event::ControllerAxisMotion { which, axis, value, .. } => {
+                let pushed = if value > DEAD_ZONE {
+                    Some(true)
+                } else if value < -DEAD_ZONE {
+                    Some(false)
+                } else {
+                    None
+                };
+                let held = self.held
+                    .iter()
+                    .filter_map(|&(id, input)| match input {
+                        Input::Axis(held_axis, positive) if id == which && held_axis == axis => {
+                            Some(positive)
+                        }
+                        _ => None,
+                    })
+                    .next();
+                if pushed == held {
+                    return Vec::new();
+                }
+                let mut events = Vec::new();
+                if let Some(positive) = held {
+                    events.extend(self.release(which, Input::Axis(axis, positive)));
+                }
+                if let Some(positive) = pushed {
+                    events.extend(self.press(which, Input::Axis(axis, positive)));
+                }
+                events
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn press(&mut self, which: i32, input: Input) -> Vec<ControllerEvent> {
+        if self.held.contains(&(which, input)) {
+            return Vec::new();
+        }
+        self.held.push((which, input));
+        vec![ControllerEvent::Pressed(input)]
+    }
+
+    fn release(&mut self, which: i32, input: Input) -> Vec<ControllerEvent> {
+        if !self.held.contains(&(which, input)) {
+            return Vec::new();
+        }
+        self.held.retain(|&held| held != (which, input));
+        vec![ControllerEvent::Released(input)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::controller::{Axis, Button};
+
+    use super::*;
+
+    /// The inputs pressed (`true`) and released (`false`) by the events
+    fn messages(events: Vec<ControllerEvent>) -> Vec<(bool, Input)> {
+        events.into_iter()
+            .filter_map(|event| match event {
+                ControllerEvent::Pressed(input) => Some((true, input)),
+                ControllerEvent::Released(input) => Some((false, input)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn button(which: i32, button: Button, down: bool) -> Event {
+        if down {
+            Event::ControllerButtonDown {
+                timestamp: 0,
+                which: which,
+                button: button,
+            }
+        } else {
+            Event::ControllerButtonUp {
+                timestamp: 0,
+                which: which,
+                button: button,
+            }
+        }
+    }
+
+    fn axis(which: i32, axis: Axis, value: i16) -> Event {
+        Event::ControllerAxisMotion {
+            timestamp: 0,
+            which: which,
+            axis: axis,
+            value: value,
+        }
+    }
+
+    #[test]
+    fn buttons_are_pressed_and_released() {
+        let mut translator = ControllerTranslator::new();
+        let a = Input::Button(Button::A);
+        assert_eq!(messages(translator.translate(&button(0, Button::A, true))),
+                   vec![(true, a)]);
+        assert!(messages(translator.translate(&button(0, Button::A, true))).is_empty());
+        assert_eq!(messages(translator.translate(&button(0, Button::A, false))),
+                   vec![(false, a)]);
+        assert!(messages(translator.translate(&button(0, Button::A, false))).is_empty());
+    }
+
+    #[test]
+    fn axes_are_pressed_past_the_dead_zone() {
+        let mut translator = ControllerTranslator::new();
+        let right = Input::Axis(Axis::LeftX, true);
+        let left = Input::Axis(Axis::LeftX, false);
+        assert!(messages(translator.translate(&axis(0, Axis::LeftX, DEAD_ZONE))).is_empty());
+        assert_eq!(messages(translator.translate(&axis(0, Axis::LeftX, DEAD_ZONE + 1))),
+                   vec![(true, right)]);
+        assert!(messages(translator.translate(&axis(0, Axis::LeftX, 32767))).is_empty());
+        // straight from one end to the other
+        assert_eq!(messages(translator.translate(&axis(0, Axis::LeftX, -32768))),
+                   vec![(false, right), (true, left)]);
+        assert_eq!(messages(translator.translate(&axis(0, Axis::LeftX, -DEAD_ZONE))),
+                   vec![(false, left)]);
+        assert!(messages(translator.translate(&axis(0, Axis::LeftX, 0))).is_empty());
+    }
+
+    #[test]
+    fn unplugging_releases_what_was_held() {
+        let mut translator = ControllerTranslator::new();
+        translator.translate(&button(0, Button::A, true));
+        translator.translate(&axis(0, Axis::LeftY, 20000));
+        translator.translate(&button(1, Button::B, true));
+        let events = translator.translate(&Event::ControllerDeviceRemoved {
+            timestamp: 0,
+            which: 0,
+        });
+        match events.last() {
+            Some(&ControllerEvent::Removed(0)) => {}
+            other => panic!("Expected the controller to be removed, got {:?}", other),
+        }
+        assert_eq!(messages(events),
+                   vec![(false, Input::Button(Button::A)),
+                        (false, Input::Axis(Axis::LeftY, true))]);
+        // the other controller keeps its button held
+        assert_eq!(messages(translator.translate(&button(1, Button::B, false))),
+                   vec![(false, Input::Button(Button::B))]);
+        assert!(messages(translator.translate(&button(0, Button::A, false))).is_empty());
+    }
+}
@@ -1,20 +1,81 @@
-use engine::font::FontCache;
+use std::fmt::Debug;
+use std::path::Path;
+
+use engine::scheduler::Scheduler;
+use engine::display::DisplaySettings;
+use engine::audio::AudioSettings;
+use engine::input::InputMap;
+use engine::message::MessageT;
+use engine::debug::DebugInfo;
+use engine::sprite::Sprites;
+
+/// Settings a game is played with, see `EngineData::game_settings`
+/// They are tuned in a file that is read again whenever it changes, and written to replays
+/// field by field.
+pub trait SettingsT: Clone + Debug {
+    /// The default settings
+    fn new() -> Self;
+    /// Reads settings tuned in a file
+    fn load<P: AsRef<Path>>(path: P) -> Result<Self, String>;
+    /// Names of all the fields, as accepted by `get` and `set`
+    fn fields() -> &'static [&'static str];
+    fn get(&self, key: &str) -> Option<String>;
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String>;
+}
 
 /// Model
-/// For now it just holds the message to display and running state of the game
+/// Holds the running state of the game and everything the engine shares with the states;
+/// generic over the game's message type `M` and its settings `S`
 #[derive(Debug)]
-pub struct EngineData {
+pub struct EngineData<M: MessageT, S> {
     pub running: bool,
+    /// Logical size of the drawing area, the window is scaled to it according to `display`
     pub window_size: (u32, u32),
-    pub font_cache: FontCache,
+    /// Fullscreen and scaling settings of the window
+    pub display: DisplaySettings,
+    /// Seed for new games; a random one is picked for every game when `None`
+    pub seed: Option<u32>,
+    /// Settings every new game starts with
+    pub game_settings: S,
+    /// Messages to be sent later, see `Scheduler`
+    pub scheduler: Scheduler<M>,
+    /// Readouts of the debug overlay
+    pub debug: DebugInfo,
+    /// Sprites the states draw entities with, see `Engine::load_sprites`
+    pub sprites: Sprites,
+    /// Volume of the sound effects
+    pub audio: AudioSettings,
+    /// Key bindings, button messages are turned into action messages with it
+    pub input: InputMap<M::Action>,
+    /// Messages queued with `send`, handed to the engine after the current one
+    outgoing: Vec<M>,
 }
 
-impl EngineData {
-    pub fn new(font_cache: FontCache) -> EngineData {
+impl<M: MessageT, S: SettingsT> EngineData<M, S> {
+    pub fn new() -> EngineData<M, S> {
         EngineData {
             running: true,
             window_size: (1024, 768),
-            font_cache: font_cache,
+            display: DisplaySettings::new(),
+            seed: None,
+            game_settings: S::new(),
+            scheduler: Scheduler::new(),
+            debug: DebugInfo::new(),
+            sprites: Sprites::new(),
+            audio: AudioSettings::new(),
+            input: InputMap::new(),
+            outgoing: Vec::new(),
         }
     }
+
+    /// Queues a message for the engine, for states that have more to say than the single
+    /// message `process_message` returns (e.g. a sound along with a tick)
+    pub fn send(&mut self, msg: M) {
+        self.outgoing.push(msg);
+    }
+
+    /// Takes the messages queued with `send`
+    pub fn take_sent(&mut self) -> Vec<M> {
+        ::std::mem::replace(&mut self.outgoing, Vec::new())
+    }
 }
@@ -0,0 +1,90 @@
+use sdl2::pixels::Color::RGB;
+use sdl2::rect::Rect;
+
+use engine::error::OrLog;
+use engine::font::RenderableText;
+use engine::render::RenderBackend;
+
+/// Description of a state on the stack, as shown by the debug overlay
+#[derive(Debug, Clone)]
+pub struct StateInfo {
+    /// Name the state is registered under
+    pub name: String,
+    pub type_name: &'static str,
+    pub fullscreen: bool,
+    /// Whatever the state reports about itself, see `StateT::debug_details`
+    pub details: Vec<String>,
+}
+
+/// Readouts of the debug overlay, updated by the engine while the overlay is shown
+#[derive(Debug)]
+pub struct DebugInfo {
+    pub enabled: bool,
+    /// Duration of the last frame in milliseconds
+    pub frame_time: u32,
+    /// Frames per second, smoothed over the last frames
+    pub fps: f32,
+    /// Length of the message queue when the last simulation step started
+    pub queue_len: usize,
+    /// Contents of the states stack, from the bottom up
+    pub states: Vec<StateInfo>,
+}
+
+impl DebugInfo {
+    pub fn new() -> DebugInfo {
+        DebugInfo {
+            enabled: false,
+            frame_time: 0,
+            fps: 0.0,
+            queue_len: 0,
+            states: Vec::new(),
+        }
+    }
+
+    pub fn record_frame(&mut self, frame_time: u32) {
+        self.frame_time = frame_time;
+        if frame_time > 0 {
+            let fps = 1000.0 / frame_time as f32;
+            self.fps = if self.fps == 0.0 {
+                fps
+            } else {
+                self.fps * 0.9 + fps * 0.1
+            };
+        }
+    }
+
+    /// Text lines of the overlay
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("FPS: {:.1} ({} ms)", self.fps, self.frame_time),
+                             format!("Queue: {}", self.queue_len),
+                             "Stack:".to_string()];
+        for state in self.states.iter().rev() {
+            lines.push(format!("  {} ({}){}",
+                               state.name,
+                               state.type_name,
+                               if state.fullscreen { " fullscreen" } else { "" }));
+            for detail in &state.details {
+                lines.push(format!("    {}", detail));
+            }
+        }
+        lines
+    }
+}
+
+/// Debug overlay
+/// Drawn by the engine over all the states while `DebugInfo::enabled` is set. It is not on
+/// the states stack, so showing it neither covers the game nor takes any messages.
+pub struct DebugOverlay;
+
+impl DebugOverlay {
+    pub fn render(&self, r: &mut RenderBackend, debug: &DebugInfo) {
+        let mut y = 5;
+        for line in debug.lines() {
+            let text = RenderableText::with_color(line, (255, 255, 0, 255));
+            let (width, height) = r.text_size("default", text.text()).or_log().unwrap_or((0, 0));
+            r.fill_rect(Rect::new(5, y, width, height), RGB(0, 0, 0)).or_log();
+            r.draw_text("default", &text, 5, y).or_log();
+            y += height as i32 + 2;
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::cmp;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use sdl2::rect::Rect;
+
+/// How the logical drawing area is fitted into a window of a different size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalingPolicy {
+    /// Fills the whole window, the aspect ratio is not kept
+    Stretch,
+    /// Scales as much as fits while keeping the aspect ratio, with black bars on the sides
+    Letterbox,
+    /// Same as `Letterbox`, but only by whole multiples, so pixels stay sharp
+    IntegerScale,
+}
+
+/// Fullscreen flavour used when fullscreen is on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FullscreenMode {
+    /// Borderless window covering the desktop, keeps the desktop resolution
+    Desktop,
+    /// Changes the video mode
+    Exclusive,
+}
+
+impl Display for ScalingPolicy {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let name = match *self {
+            ScalingPolicy::Stretch => "Stretch",
+            ScalingPolicy::Letterbox => "Letterbox",
+            ScalingPolicy::IntegerScale => "Integer",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for FullscreenMode {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let name = match *self {
+            FullscreenMode::Desktop => "Desktop",
+            FullscreenMode::Exclusive => "Exclusive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Changes to the window settings, applied by the engine
+#[derive(Debug, Clone, Copy)]
+pub enum DisplayCommand {
+    ToggleFullscreen,
+    NextFullscreenMode,
+    NextScaling,
+    TogglePauseOnFocusLoss,
+}
+
+/// Window settings that are kept between runs, in the options file (see `engine::options`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplaySettings {
+    pub fullscreen: bool,
+    pub fullscreen_mode: FullscreenMode,
+    pub scaling: ScalingPolicy,
+    /// Whether the engine tells the states when the window loses focus, so they can pause
+    pub pause_on_focus_loss: bool,
+}
+
+impl DisplaySettings {
+    pub fn new() -> DisplaySettings {
+        DisplaySettings {
+            fullscreen: false,
+            fullscreen_mode: FullscreenMode::Desktop,
+            scaling: ScalingPolicy::Letterbox,
+            pause_on_focus_loss: true,
+        }
+    }
+
+    pub fn next_scaling(&mut self) {
+        self.scaling = match self.scaling {
+            ScalingPolicy::Stretch => ScalingPolicy::Letterbox,
+            ScalingPolicy::Letterbox => ScalingPolicy::IntegerScale,
+            ScalingPolicy::IntegerScale => ScalingPolicy::Stretch,
+        };
+    }
+
+    pub fn next_fullscreen_mode(&mut self) {
+        self.fullscreen_mode = match self.fullscreen_mode {
+            FullscreenMode::Desktop => FullscreenMode::Exclusive,
+            FullscreenMode::Exclusive => FullscreenMode::Desktop,
+        };
+    }
+
+    /// Changes the setting called `key`, as written by `write`, e.g. `scaling letterbox`
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match (key, value) {
+            ("fullscreen", value) => self.fullscreen = parse_switch(value)?,
+            ("fullscreen_mode", "desktop") => self.fullscreen_mode = FullscreenMode::Desktop,
+            ("fullscreen_mode", "exclusive") => self.fullscreen_mode = FullscreenMode::Exclusive,
+            ("scaling", "stretch") => self.scaling = ScalingPolicy::Stretch,
+            ("scaling", "letterbox") => self.scaling = ScalingPolicy::Letterbox,
+            ("scaling", "integer") => self.scaling = ScalingPolicy::IntegerScale,
+            ("pause_on_focus_loss", value) => self.pause_on_focus_loss = parse_switch(value)?,
+            ("fullscreen_mode", _) | ("scaling", _) => {
+                return Err(format!("invalid {} {}", key, value))
+            }
+            _ => return Err(format!("unknown setting {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Writes one `setting value` line per setting, read back with `set`
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "fullscreen {}", if self.fullscreen { "on" } else { "off" })?;
+        writeln!(out,
+                 "fullscreen_mode {}",
+                 self.fullscreen_mode.to_string().to_lowercase())?;
+        writeln!(out, "scaling {}", self.scaling.to_string().to_lowercase())?;
+        writeln!(out,
+                 "pause_on_focus_loss {}",
+                 if self.pause_on_focus_loss { "on" } else { "off" })
+    }
+
+    /// Reads the `display.cfg` of older versions, which is now part of the options file;
+    /// lines that can not be understood are skipped
+    pub fn load_legacy<P: AsRef<Path>>(path: P) -> io::Result<DisplaySettings> {
+        let mut settings = DisplaySettings::new();
+        let file = File::open(path)?;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                let _ = settings.set(key, value);
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// `on` or `off`
+pub fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected on or off, not {}", value)),
+    }
+}
+
+/// Part of an `output` sized window the `logical` drawing area is drawn to, along with the
+/// horizontal and vertical scale
+pub fn viewport(policy: ScalingPolicy,
+                logical: (u32, u32),
+                output: (u32, u32))
+                -> (Rect, f32, f32) {
+    let scale_x = output.0 as f32 / logical.0 as f32;
+    let scale_y = output.1 as f32 / logical.1 as f32;
+    let scale = match policy {
+        ScalingPolicy::Stretch => {
+            return (Rect::new(0, 0, output.0, output.1), scale_x, scale_y);
+        }
+        ScalingPolicy::Letterbox => scale_x.min(scale_y),
+        ScalingPolicy::IntegerScale => {
+            // windows smaller than the logical size get scaled down anyway
+            let whole = cmp::min(output.0 / logical.0, output.1 / logical.1);
+            if whole == 0 {
+                scale_x.min(scale_y)
+            } else {
+                whole as f32
+            }
+        }
+    };
+    let width = (logical.0 as f32 * scale) as u32;
+    let height = (logical.1 as f32 * scale) as u32;
+    (Rect::new((output.0.saturating_sub(width) / 2) as i32,
+               (output.1.saturating_sub(height) / 2) as i32,
+               cmp::max(width, 1),
+               cmp::max(height, 1)),
+     scale,
+     scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::rect::Rect;
+
+    use super::{viewport, ScalingPolicy};
+
+    #[test]
+    fn stretching_fills_the_window() {
+        assert_eq!(viewport(ScalingPolicy::Stretch, (640, 480), (1280, 720)),
+                   (Rect::new(0, 0, 1280, 720), 2.0, 1.5));
+    }
+
+    #[test]
+    fn letterboxing_keeps_the_aspect_ratio() {
+        assert_eq!(viewport(ScalingPolicy::Letterbox, (640, 480), (1280, 960)),
+                   (Rect::new(0, 0, 1280, 960), 2.0, 2.0));
+        // bars on the sides of a wide window, above and below in a tall one
+        assert_eq!(viewport(ScalingPolicy::Letterbox, (640, 480), (1280, 720)),
+                   (Rect::new(160, 0, 960, 720), 1.5, 1.5));
+        assert_eq!(viewport(ScalingPolicy::Letterbox, (640, 480), (640, 960)),
+                   (Rect::new(0, 240, 640, 480), 1.0, 1.0));
+    }
+
+    #[test]
+    fn integer_scaling_uses_whole_multiples() {
+        assert_eq!(viewport(ScalingPolicy::IntegerScale, (640, 480), (1600, 1000)),
+                   (Rect::new(160, 20, 1280, 960), 2.0, 2.0));
+        assert_eq!(viewport(ScalingPolicy::IntegerScale, (640, 480), (1280, 960)),
+                   (Rect::new(0, 0, 1280, 960), 2.0, 2.0));
+    }
+
+    #[test]
+    fn integer_scaling_scales_down_small_windows() {
+        let (rect, scale_x, scale_y) =
+            viewport(ScalingPolicy::IntegerScale, (640, 480), (320, 300));
+        assert_eq!(rect, Rect::new(0, 30, 320, 240));
+        assert_eq!((scale_x, scale_y), (0.5, 0.5));
+    }
+}
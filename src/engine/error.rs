@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use log::Category;
+
+/// Everything that can go wrong in the engine
+#[derive(Debug)]
+pub enum EngineError {
+    /// SDL or one of its subsystems could not be initialized
+    Init(String),
+    /// The window or its renderer could not be created
+    Window(String),
+    /// The font could not be read or turned into a glyph atlas
+    Font(String, String),
+    /// The image could not be read or turned into a texture
+    Texture(String, String),
+    /// No font was loaded under the key
+    MissingFont(String),
+    /// No texture was registered under the key
+    MissingTexture(String),
+    /// The resource was neither in any of the searched directories nor embedded
+    MissingResource(String, Vec<PathBuf>),
+    /// A draw call or render target switch failed
+    Render(String),
+    Io(io::Error),
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            EngineError::Init(ref e) => write!(f, "Could not initialize SDL: {}", e),
+            EngineError::Window(ref e) => write!(f, "Could not create the window: {}", e),
+            EngineError::Font(ref key, ref e) => write!(f, "Could not load font {}: {}", key, e),
+            EngineError::Texture(ref key, ref e) => {
+                write!(f, "Could not load texture {}: {}", key, e)
+            }
+            EngineError::MissingFont(ref key) => write!(f, "Font not found: {}", key),
+            EngineError::MissingTexture(ref key) => write!(f, "Texture not found: {}", key),
+            EngineError::MissingResource(ref name, ref searched) => {
+                let searched: Vec<String> = searched.iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect();
+                write!(f, "Could not find {}, looked in: {}", name, searched.join(", "))
+            }
+            EngineError::Render(ref e) => write!(f, "Drawing failed: {}", e),
+            EngineError::Io(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// `Display` carries the messages
+impl Error for EngineError {}
+
+impl From<io::Error> for EngineError {
+    fn from(e: io::Error) -> EngineError {
+        EngineError::Io(e)
+    }
+}
+
+/// Number of distinct errors `or_log` remembers; past that it forgets them all, so a game
+/// running for hours does not keep every message it ever logged
+const MAX_REPORTED: usize = 64;
+
+lazy_static! {
+    /// Errors `or_log` has already reported
+    static ref REPORTED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Logs errors that should not stop the game, e.g. a failed draw call
+pub trait OrLog<T> {
+    /// Logs the error, if any, and turns the result into an `Option`. Draw calls are repeated
+    /// every frame, so each distinct error is only logged once, unless it was forgotten to
+    /// make room for others (see `MAX_REPORTED`).
+    fn or_log(self) -> Option<T>;
+}
+
+impl<T> OrLog<T> for Result<T, EngineError> {
+    fn or_log(self) -> Option<T> {
+        match self {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let message = e.to_string();
+                let mut reported = REPORTED.lock().unwrap();
+                if !reported.contains(&message) {
+                    if reported.len() >= MAX_REPORTED {
+                        reported.clear();
+                    }
+                    error!(Category::Engine, "{}", message);
+                    reported.insert(message);
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EngineError, OrLog, MAX_REPORTED, REPORTED};
+
+    #[test]
+    fn reported_errors_are_bounded() {
+        for index in 0..MAX_REPORTED * 3 {
+            let result: Result<(), EngineError> = Err(EngineError::Render(index.to_string()));
+            assert_eq!(result.or_log(), None);
+        }
+        assert!(REPORTED.lock().unwrap().len() <= MAX_REPORTED);
+    }
+}
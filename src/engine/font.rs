@@ -1,6 +1,5 @@
 use std::collections::HashMap;
-use std::fmt::{Debug, Formatter, Error};
-use std::path::PathBuf;
+use std::fmt::{Debug, Display, Formatter, Error};
 use std::convert::From;
 
 use sdl2::ttf::{Sdl2TtfContext, Font, GlyphMetrics};
@@ -8,11 +7,15 @@ use sdl2::render::{Renderer, Texture};
 use sdl2::rect::Rect;
 use sdl2::pixels::{PixelFormatEnum, Color};
 use sdl2::surface::Surface;
+use sdl2::rwops::RWops;
+
+use engine::error::EngineError;
+use log::Category;
 
 const GLYPH_SET: &'static str = "/\\| _-+=abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!.,'\":;абвгдеёжзийклмнопрстуфхцчшщъыьэюяАБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ";
 
-#[derive(Debug, Clone, Copy)]
-pub struct ColorMod(u8, u8, u8, u8);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorMod(pub u8, pub u8, pub u8, pub u8);
 
 impl Default for ColorMod {
     fn default() -> Self {
@@ -26,6 +29,7 @@ impl From<(u8, u8, u8, u8)> for ColorMod {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct RenderableText {
     text: String,
     color_mod: ColorMod,
@@ -49,6 +53,14 @@ impl RenderableText {
     pub fn set_color(&mut self, cm: ColorMod) {
         self.color_mod = cm;
     }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn color_mod(&self) -> ColorMod {
+        self.color_mod
+    }
 }
 
 impl From<String> for RenderableText {
@@ -83,25 +95,48 @@ impl FontCache {
         }
     }
 
+    /// Loads a TTF font from the contents of a font file and makes it available under `key`
     pub fn load_font<T: Into<String>>(&mut self,
                                       r: &mut Renderer,
                                       key: T,
-                                      path: PathBuf,
-                                      size: u16) {
-        let font = self.context.load_font(path, size).unwrap();
-        let font_atlas = Self::generate_font_atlas(r, &font);
+                                      data: &[u8],
+                                      size: u16)
+                                      -> Result<(), EngineError> {
+        let key: String = key.into();
+        info!(Category::Font, "Loading font {} at size {}", key, size);
+        let rwops = RWops::from_bytes(data).map_err(|e| EngineError::Font(key.clone(), e))?;
+        let font = self.context
+            .load_font_from_rwops(rwops, size)
+            .map_err(|e| EngineError::Font(key.clone(), e))?;
+        let font_atlas = Self::generate_font_atlas(r, &font)
+            .map_err(|e| EngineError::Font(key.clone(), e.to_string()))?;
+
+        self.cache.insert(key, font_atlas);
+        Ok(())
+    }
 
-        self.cache.insert(key.into(), font_atlas);
+    /// Dimensions of `text` rendered with the font under `key`; every glyph takes the same space
+    pub fn text_size(&self, key: &str, text: &str) -> Result<(u32, u32), EngineError> {
+        let font = self.cache.get(key).ok_or_else(|| EngineError::MissingFont(key.to_string()))?;
+        Ok((text.chars().count() as u32 * font.max_size.0, font.max_size.1))
     }
 
+    /// Metrics of the glyphs of `GLYPH_SET` the font has, along with the largest advance and
+    /// height among them
     fn get_font_metrics(f: &Font) -> (HashMap<char, GlyphMetrics>, u32, u32) {
         let mut max_x: u32 = 0;
         let mut max_y: u32 = 0;
 
         (GLYPH_SET.to_string()
              .chars()
-             .map(|x| {
-                let m = f.find_glyph_metrics(x).unwrap();
+             .filter_map(|x| {
+                let m = match f.find_glyph_metrics(x) {
+                    Some(m) => m,
+                    None => {
+                        debug!(Category::Font, "The font has no glyph for {}", x);
+                        return None;
+                    }
+                };
                 max_x = if m.advance > max_x as i32 {
                     m.advance as u32
                 } else {
@@ -112,68 +147,75 @@ impl FontCache {
                 } else {
                     max_y
                 };
-                (x, m)
+                Some((x, m))
             })
              .collect(),
          max_x,
          max_y)
     }
 
-    pub fn generate_font_atlas(r: &mut Renderer, f: &Font) -> FontAtlas {
+    pub fn generate_font_atlas(r: &mut Renderer, f: &Font) -> Result<FontAtlas, EngineError> {
         let (metrics, max_x, max_y) = FontCache::get_font_metrics(f);
-        r.render_target().unwrap().create_and_set(PixelFormatEnum::RGBA8888, 256, 256).unwrap();
+        set_render_target(r, 256, 256)?;
         r.clear();
+        // drawing goes back to the window even if a glyph could not be drawn
+        let glyphs = FontCache::draw_glyphs(r, f, &metrics, (max_x, max_y));
+        let texture = reset_render_target(r)?;
+
+        Ok(FontAtlas {
+            texture: texture,
+            glyphs: glyphs?,
+            metrics: metrics,
+            max_size: (max_x, max_y),
+        })
+    }
+
+    /// Draws every glyph with metrics to the current render target, returns where they are
+    fn draw_glyphs(r: &mut Renderer,
+                   f: &Font,
+                   metrics: &HashMap<char, GlyphMetrics>,
+                   max_size: (u32, u32))
+                   -> Result<HashMap<char, Rect>, EngineError> {
+        let (max_x, max_y) = max_size;
         let mut glyphs_map: HashMap<char, Rect> = HashMap::new();
 
         let mut current_x: u32 = 0;
         let mut current_y: u32 = 0;
-        for glyph in GLYPH_SET.to_string().chars() {
+        for glyph in GLYPH_SET.chars().filter(|c| metrics.contains_key(c)) {
             if current_x + max_x > 256 {
                 current_x = 0;
                 current_y += max_y;
             }
             let glyph_surface: Surface<'static> = f.render_char(glyph)
                 .solid(Color::RGBA(255, 255, 255, 0))
-                .expect("Could not render glyph!");
-            let glyph_texture = r.create_texture_from_surface(&glyph_surface).unwrap();
+                .map_err(render_error)?;
+            let glyph_texture = r.create_texture_from_surface(&glyph_surface)
+                .map_err(render_error)?;
             let glyph_rect: Rect = Rect::new(current_x as i32, current_y as i32, max_x, max_y);
-            r.copy(&glyph_texture, None, Some(glyph_rect)).unwrap();
+            r.copy(&glyph_texture, None, Some(glyph_rect)).map_err(EngineError::Render)?;
             glyphs_map.insert(glyph, glyph_rect);
             current_x += max_x;
         }
-
-        FontAtlas {
-            texture: r.render_target().unwrap().reset().unwrap().unwrap(),
-            glyphs: glyphs_map,
-            metrics: metrics,
-            max_size: (max_x, max_y),
-        }
+        Ok(glyphs_map)
     }
 
     pub fn render_texture<'a, T: Into<&'a str>, M: Into<RenderableText>>(&mut self,
                           r: &mut Renderer,
                           key: T,
-                          text: M) -> Result<Texture, String> {
+                          text: M) -> Result<Texture, EngineError> {
         let key_str = key.into();
         let renderable = text.into();
-        {
-            let font = self.cache.get_mut(key_str).ok_or_else(|| "Font not found".to_string())?;
-
-            r.render_target()
-                .unwrap()
-                .create_and_set(PixelFormatEnum::RGBA8888,
-                                renderable.text.len() as u32 * font.max_size.0,
-                                font.max_size.1)
-                .unwrap();
-        }
-
-        self.render_text(r, key_str, renderable, 0, 0).unwrap();
-
-        r.render_target()
-            .unwrap()
-            .reset()
-            .unwrap()
-            .ok_or_else(|| "Can not render texture!".to_string())
+        let size = {
+            let font = self.cache
+                .get(key_str)
+                .ok_or_else(|| EngineError::MissingFont(key_str.to_string()))?;
+            (renderable.text.chars().count() as u32 * font.max_size.0, font.max_size.1)
+        };
+
+        set_render_target(r, size.0, size.1)?;
+        let rendered = self.render_text(r, key_str, renderable, 0, 0);
+        let texture = reset_render_target(r)?;
+        rendered.map(|_| texture)
     }
 
     pub fn render_text<'a, T: Into<&'a str>, M: Into<RenderableText>>(&mut self,
@@ -182,8 +224,11 @@ impl FontCache {
                                                                       text: M,
                                                                       x: i32,
                                                                       y: i32)
-                                                                      -> Result<(), String> {
-        let font = self.cache.get_mut(key.into()).ok_or_else(|| "Font not found".to_string())?;
+                                                                      -> Result<(), EngineError> {
+        let key_str = key.into();
+        let font = self.cache
+            .get_mut(key_str)
+            .ok_or_else(|| EngineError::MissingFont(key_str.to_string()))?;
 
         let current_color_mod = font.texture.color_mod();
         let current_alpha_mod = font.texture.alpha_mod();
@@ -194,20 +239,52 @@ impl FontCache {
         font.texture.set_alpha_mod(a_mod);
 
         let mut current_x: i32 = x;
+        let mut result = Ok(());
 
         for character in renderable.text.chars() {
-            r.copy(&font.texture,
-                      font.glyphs.get(&character).cloned(),
-                      Some(Rect::new(current_x, y, font.max_size.0, font.max_size.1)))?;
+            result = r.copy(&font.texture,
+                            font.glyphs.get(&character).cloned(),
+                            Some(Rect::new(current_x, y, font.max_size.0, font.max_size.1)))
+                .map_err(EngineError::Render);
+            if result.is_err() {
+                break;
+            }
             current_x += font.max_size.0 as i32;
         }
 
+        // the modulation is restored even if drawing failed, the atlas is shared
         font.texture.set_color_mod(current_color_mod.0,
                                    current_color_mod.1,
                                    current_color_mod.2);
         font.texture.set_alpha_mod(current_alpha_mod);
 
-        Ok(())
+        result
+    }
+}
+
+fn render_error<E: Display>(e: E) -> EngineError {
+    EngineError::Render(e.to_string())
+}
+
+/// Makes a new `width` x `height` texture the target everything is drawn to
+fn set_render_target(r: &mut Renderer, width: u32, height: u32) -> Result<(), EngineError> {
+    let mut target = match r.render_target() {
+        Some(target) => target,
+        None => return Err(EngineError::Render("Render targets are not supported".to_string())),
+    };
+    target.create_and_set(PixelFormatEnum::RGBA8888, width, height).map_err(render_error)?;
+    Ok(())
+}
+
+/// Draws to the window again, returns the texture drawn to since `set_render_target`
+fn reset_render_target(r: &mut Renderer) -> Result<Texture, EngineError> {
+    let mut target = match r.render_target() {
+        Some(target) => target,
+        None => return Err(EngineError::Render("Render targets are not supported".to_string())),
+    };
+    match target.reset().map_err(render_error)? {
+        Some(texture) => Ok(texture),
+        None => Err(EngineError::Render("Nothing was drawn to a texture".to_string())),
     }
 }
 
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+use std::str::FromStr;
+
+use sdl2::keyboard::Keycode;
+use sdl2::controller::{Axis, Button};
+
+use engine::message::{EngineEvent, MessageT};
+
+/// Physical input an action can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(Keycode),
+    /// A game controller button
+    Button(Button),
+    /// A game controller stick or trigger pushed past the dead zone (see
+    /// `engine::controller`), towards its positive end if the flag is set
+    Axis(Axis, bool),
+}
+
+impl Input {
+    /// Whether the input is on a game controller rather than the keyboard
+    pub fn is_controller(&self) -> bool {
+        match *self {
+            Input::Key(_) => false,
+            Input::Button(_) | Input::Axis(_, _) => true,
+        }
+    }
+
+    /// How the input is written in files, e.g. `key Left Shift`, `button a` or
+    /// `axis lefty -`; read back with `from_str`
+    pub fn config_name(&self) -> String {
+        match *self {
+            Input::Key(k) => format!("key {}", k.name()),
+            Input::Button(b) => format!("button {}", b.string()),
+            Input::Axis(axis, positive) => {
+                format!("axis {} {}", axis.string(), if positive { "+" } else { "-" })
+            }
+        }
+    }
+}
+
+/// Names shown to the player
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Input::Key(k) => write!(f, "{}", k.name()),
+            Input::Button(b) => write!(f, "Pad {}", b.string()),
+            Input::Axis(axis, positive) => {
+                write!(f, "Pad {}{}", axis.string(), if positive { "+" } else { "-" })
+            }
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Input, String> {
+        let mut parts = s.trim().splitn(2, ' ');
+        let kind = parts.next().unwrap_or("");
+        let name = parts.next().unwrap_or("").trim();
+        let input = match kind {
+            "key" => Keycode::from_name(name).map(Input::Key),
+            "button" => Button::from_string(name).map(Input::Button),
+            "axis" => {
+                let mut axis_parts = name.split_whitespace();
+                match (axis_parts.next().and_then(Axis::from_string), axis_parts.next()) {
+                    (Some(axis), Some("+")) => Some(Input::Axis(axis, true)),
+                    (Some(axis), Some("-")) => Some(Input::Axis(axis, false)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        input.ok_or_else(|| format!("Unknown input: {}", s))
+    }
+}
+
+/// What the player can do, as defined by the game
+/// Actions are written to the options file by their lowercase name and read back with
+/// `from_str`.
+pub trait ActionT: Copy + Eq + Hash + Debug + Display + FromStr<Err = String> {
+    /// Every action, in the order they are listed
+    fn all() -> Vec<Self>;
+    /// Inputs the actions are bound to unless the player changes it
+    fn default_bindings() -> Vec<(Input, Self)>;
+}
+
+/// Input layer
+/// Maps physical inputs to the actions the states understand. An action can be bound to
+/// any number of inputs, an input triggers at most one action. The engine passes every
+/// keyboard and controller message through `translate`, so the states get
+/// `EngineEvent::ActionPressed` and `EngineEvent::ActionReleased` for bound inputs and the
+/// raw messages for the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputMap<A> {
+    bindings: HashMap<Input, A>,
+    /// Bound inputs held down; an action stays pressed while any of its inputs is held
+    held: HashSet<Input>,
+    /// While set nothing is translated, so that the next button pressed can be bound to an
+    /// action even if it already triggers another one
+    pub listening: bool,
+}
+
+impl<A: ActionT> InputMap<A> {
+    /// The default bindings of the game, see `ActionT::default_bindings`
+    pub fn new() -> InputMap<A> {
+        let mut input = InputMap::empty();
+        for (bound, action) in A::default_bindings() {
+            input.bind(bound, action);
+        }
+        input
+    }
+
+    /// No bindings at all
+    pub fn empty() -> InputMap<A> {
+        InputMap {
+            bindings: HashMap::new(),
+            held: HashSet::new(),
+            listening: false,
+        }
+    }
+
+    /// Binds `input` to `action`, in addition to the other bindings of the action. The input
+    /// no longer triggers the action it was bound to before.
+    pub fn bind(&mut self, input: Input, action: A) {
+        self.bindings.insert(input, action);
+    }
+
+    /// Makes `input` the only binding of `action` on its device: a key replaces the keys, a
+    /// controller input the controller inputs
+    pub fn rebind(&mut self, action: A, input: Input) {
+        for bound in self.inputs(action) {
+            if bound.is_controller() == input.is_controller() {
+                self.unbind(bound);
+            }
+        }
+        self.bind(input, action);
+    }
+
+    pub fn unbind(&mut self, input: Input) {
+        self.bindings.remove(&input);
+    }
+
+    /// Action `input` is bound to, if any
+    pub fn action(&self, input: Input) -> Option<A> {
+        self.bindings.get(&input).cloned()
+    }
+
+    /// Inputs bound to `action`, sorted by name
+    pub fn inputs(&self, action: A) -> Vec<Input> {
+        let mut inputs: Vec<Input> = self.bindings
+            .iter()
+            .filter(|&(_, bound)| *bound == action)
+            .map(|(input, _)| *input)
+            .collect();
+        inputs.sort_by_key(|input| input.to_string());
+        inputs
+    }
+
+    /// Number of held inputs bound to `action`
+    pub fn held_count(&self, action: A) -> usize {
+        self.held.iter().filter(|&&input| self.action(input) == Some(action)).count()
+    }
+
+    /// Turns a button message into the message of the action the button is bound to; any
+    /// other message, or any message while `listening`, is returned as it is. An action bound
+    /// to several held inputs is only released along with the last of them, the releases of
+    /// the others are returned as they are.
+    pub fn translate<M: MessageT<Action = A>>(&mut self, msg: M) -> M {
+        if self.listening {
+            return msg;
+        }
+        let (input, pressed) = match msg.event() {
+            Some(EngineEvent::ButtonPressed(k)) => (Input::Key(k), true),
+            Some(EngineEvent::ButtonReleased(k)) => (Input::Key(k), false),
+            Some(EngineEvent::ControllerPressed(input)) => (input, true),
+            Some(EngineEvent::ControllerReleased(input)) => (input, false),
+            _ => return msg,
+        };
+        let action = match self.action(input) {
+            Some(action) => action,
+            None => return msg,
+        };
+        if pressed {
+            self.held.insert(input);
+            M::from_event(EngineEvent::ActionPressed(action))
+        } else {
+            self.held.remove(&input);
+            if self.held_count(action) > 0 {
+                msg
+            } else {
+                M::from_event(EngineEvent::ActionReleased(action))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::message::testing::{TestAction, TestMsg};
+    use super::*;
+
+    #[test]
+    fn config_names_are_read_back() {
+        let inputs = [Input::Key(Keycode::W),
+                      Input::Key(Keycode::LShift),
+                      Input::Key(Keycode::KpEnter),
+                      Input::Button(Button::A),
+                      Input::Button(Button::DPadUp),
+                      Input::Axis(Axis::LeftY, false),
+                      Input::Axis(Axis::TriggerRight, true)];
+        for &input in inputs.iter() {
+            assert_eq!(input.config_name().parse::<Input>(), Ok(input));
+        }
+        assert_eq!(Input::Key(Keycode::LShift).config_name(), "key Left Shift");
+        assert_eq!(Input::Axis(Axis::LeftY, false).config_name(), "axis lefty -");
+    }
+
+    #[test]
+    fn unknown_inputs_are_rejected() {
+        for text in ["", "key", "key Nope", "button nope", "axis lefty", "axis lefty ~",
+                     "stick a"]
+            .iter() {
+            assert!(text.parse::<Input>().is_err(), "{} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn bound_buttons_become_actions() {
+        let mut input = InputMap::new();
+        match input.translate(TestMsg::Event(EngineEvent::ButtonPressed(Keycode::Space))) {
+            TestMsg::Event(EngineEvent::ActionPressed(TestAction::Jump)) => {}
+            msg => panic!("Expected jumping, got {:?}", msg),
+        }
+        let released = EngineEvent::ControllerReleased(Input::Button(Button::B));
+        match input.translate(TestMsg::Event(released)) {
+            TestMsg::Event(EngineEvent::ActionReleased(TestAction::Back)) => {}
+            msg => panic!("Expected going back, got {:?}", msg),
+        }
+        match input.translate(TestMsg::Event(EngineEvent::ButtonPressed(Keycode::Q))) {
+            TestMsg::Event(EngineEvent::ButtonPressed(Keycode::Q)) => {}
+            msg => panic!("Expected Q to stay a button, got {:?}", msg),
+        }
+        input.listening = true;
+        match input.translate(TestMsg::Event(EngineEvent::ButtonPressed(Keycode::Space))) {
+            TestMsg::Event(EngineEvent::ButtonPressed(Keycode::Space)) => {}
+            msg => panic!("Expected Space to stay a button while listening, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn actions_are_released_with_the_last_held_input() {
+        let mut input = InputMap::new();
+        input.translate(TestMsg::Event(EngineEvent::ButtonPressed(Keycode::Space)));
+        input.translate(TestMsg::Event(EngineEvent::ControllerPressed(Input::Button(Button::A))));
+        assert_eq!(input.held_count(TestAction::Jump), 2);
+        match input.translate(TestMsg::Event(EngineEvent::ButtonReleased(Keycode::Space))) {
+            TestMsg::Event(EngineEvent::ButtonReleased(Keycode::Space)) => {}
+            msg => panic!("Expected to keep jumping while A is held, got {:?}", msg),
+        }
+        let released = EngineEvent::ControllerReleased(Input::Button(Button::A));
+        match input.translate(TestMsg::Event(released)) {
+            TestMsg::Event(EngineEvent::ActionReleased(TestAction::Jump)) => {}
+            msg => panic!("Expected to stop jumping, got {:?}", msg),
+        }
+        assert_eq!(input.held_count(TestAction::Jump), 0);
+    }
+
+    #[test]
+    fn rebinding_replaces_the_inputs_of_the_same_device() {
+        let mut input: InputMap<TestAction> = InputMap::new();
+        input.rebind(TestAction::Jump, Input::Key(Keycode::J));
+        assert_eq!(input.inputs(TestAction::Jump),
+                   vec![Input::Key(Keycode::J), Input::Button(Button::A)]);
+        assert_eq!(input.action(Input::Key(Keycode::Space)), None);
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use sdl2::keyboard::Keycode;
+
+use engine::audio::AudioCommand;
+use engine::display::DisplayCommand;
+use engine::input::{ActionT, Input};
+use engine::scheduler::TimerHandle;
+use engine::stack::StackCommand;
+
+/// Messages the engine sends to the states, and the requests it handles when they fall
+/// through the stack; `A` is the game's action type, see `InputMap`
+#[derive(Debug, Clone, Copy)]
+pub enum EngineEvent<A> {
+    Exit,
+    /// A simulation step of the given number of milliseconds
+    Tick(u32),
+    ButtonPressed(Keycode),
+    ButtonReleased(Keycode),
+    /// A game controller button or stick, see `engine::controller`
+    ControllerPressed(Input),
+    ControllerReleased(Input),
+    /// An input bound to the action was pressed, see `InputMap::translate`
+    ActionPressed(A),
+    ActionReleased(A),
+    /// The window lost focus or was minimized, only sent when pausing on focus loss is on
+    FocusLost,
+    /// The window got focus back or was restored
+    FocusGained,
+    /// Sent by timers scheduled with `Scheduler::after_with`
+    TimerFired(TimerHandle),
+    /// Changes the states stack
+    Stack(StackCommand),
+    /// Changes the window settings
+    Display(DisplayCommand),
+    /// Changes the volume
+    Audio(AudioCommand),
+    /// The bindings or volume in `EngineData` changed, the engine applies and saves them
+    OptionsChanged,
+}
+
+/// Message type of a game
+/// The engine is generic over it: everything it sends is made with `from_event`, and
+/// whatever falls through the states stack is looked at with `event` and `sound`. Messages
+/// that are neither are dropped with a warning, so states have to handle their own messages
+/// themselves. Messages can be typed in the console, so they are parsed with `from_str`.
+pub trait MessageT: Copy + Debug + FromStr<Err = String> {
+    /// What the player can do, independent of the inputs it is done with
+    type Action: ActionT;
+    /// Sound effects, made into samples by `Engine::sounds`
+    type Sound: Copy + Debug;
+
+    fn from_event(event: EngineEvent<Self::Action>) -> Self;
+    /// The engine event the message stands for, if any
+    fn event(&self) -> Option<EngineEvent<Self::Action>>;
+    /// The sound effect the message asks for, if any
+    fn sound(&self) -> Option<Self::Sound>;
+}
+
+/// Actions, messages and settings of a made up game, for the engine's own tests
+#[cfg(test)]
+pub mod testing {
+    use std::fmt::{self, Display, Formatter};
+    use std::path::Path;
+    use std::str::FromStr;
+
+    use sdl2::controller::Button;
+    use sdl2::keyboard::Keycode;
+
+    use engine::data::SettingsT;
+    use engine::input::{ActionT, Input};
+    use super::{EngineEvent, MessageT};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum TestAction {
+        Jump,
+        Duck,
+        Back,
+    }
+
+    impl Display for TestAction {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl FromStr for TestAction {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<TestAction, String> {
+            match s {
+                "jump" => Ok(TestAction::Jump),
+                "duck" => Ok(TestAction::Duck),
+                "back" => Ok(TestAction::Back),
+                _ => Err(format!("Unknown action: {}", s)),
+            }
+        }
+    }
+
+    impl ActionT for TestAction {
+        fn all() -> Vec<TestAction> {
+            vec![TestAction::Jump, TestAction::Duck, TestAction::Back]
+        }
+
+        fn default_bindings() -> Vec<(Input, TestAction)> {
+            vec![(Input::Key(Keycode::Space), TestAction::Jump),
+                 (Input::Button(Button::A), TestAction::Jump),
+                 (Input::Key(Keycode::Down), TestAction::Duck),
+                 (Input::Key(Keycode::Escape), TestAction::Back),
+                 (Input::Button(Button::B), TestAction::Back)]
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub enum TestMsg {
+        Event(EngineEvent<TestAction>),
+        /// Stands for the messages only the game's states know about
+        Other,
+    }
+
+    impl FromStr for TestMsg {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<TestMsg, String> {
+            match s {
+                "other" => Ok(TestMsg::Other),
+                _ => Err(format!("Unknown message: {}", s)),
+            }
+        }
+    }
+
+    impl MessageT for TestMsg {
+        type Action = TestAction;
+        type Sound = ();
+
+        fn from_event(event: EngineEvent<TestAction>) -> TestMsg {
+            TestMsg::Event(event)
+        }
+
+        fn event(&self) -> Option<EngineEvent<TestAction>> {
+            match *self {
+                TestMsg::Event(event) => Some(event),
+                TestMsg::Other => None,
+            }
+        }
+
+        fn sound(&self) -> Option<()> {
+            None
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct TestSettings {
+        pub speed: u32,
+    }
+
+    impl SettingsT for TestSettings {
+        fn new() -> TestSettings {
+            TestSettings { speed: 10 }
+        }
+
+        fn load<P: AsRef<Path>>(path: P) -> Result<TestSettings, String> {
+            Err(format!("Can not load {}", path.as_ref().display()))
+        }
+
+        fn fields() -> &'static [&'static str] {
+            &["speed"]
+        }
+
+        fn get(&self, key: &str) -> Option<String> {
+            match key {
+                "speed" => Some(self.speed.to_string()),
+                _ => None,
+            }
+        }
+
+        fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+            match key {
+                "speed" => {
+                    self.speed = value.parse().map_err(|_| format!("Invalid value: {}", value))?
+                }
+                _ => return Err(format!("Unknown setting: {}", key)),
+            }
+            Ok(())
+        }
+    }
+}
@@ -2,47 +2,128 @@ pub mod state;
 pub mod data;
 pub mod context;
 pub mod font;
-
+pub mod render;
+pub mod terminal;
+pub mod capture;
+pub mod scheduler;
+pub mod registry;
+pub mod stack;
+pub mod display;
+pub mod debug;
+pub mod console;
+pub mod error;
+pub mod watch;
+pub mod sprite;
+pub mod audio;
+pub mod synth;
+pub mod input;
+pub mod options;
+pub mod controller;
+pub mod message;
+pub mod replay;
+
+use std::cmp;
 use std::collections::{HashSet, VecDeque};
-use sdl2::{EventPump, VideoSubsystem, TimerSubsystem};
-use sdl2::render::{Renderer, Texture};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use rand;
+use sdl2::{EventPump, GameControllerSubsystem, VideoSubsystem, TimerSubsystem};
+use sdl2::controller::GameController;
+use sdl2::render::Renderer;
 use sdl2::video::Window;
 use sdl2::ttf::Sdl2TtfContext;
 use sdl2::pixels::Color::RGB;
 use sdl2::keyboard::Keycode;
 use sdl2::rect::Rect;
 
-use engine::data::EngineData;
-use super::msg::{Msg, MenuMsg};
+use engine::data::{EngineData, SettingsT};
+use engine::message::{EngineEvent, MessageT};
+use engine::replay::{Recorder, Replay};
+use resources::{self, Resources};
+use log::Category;
 use engine::context::SDL2Context;
-use engine::state::StateT;
-use game::state::pixel::GameState;
-use game::state::menu::{MenuState, MenuPosition};
-use game::state::static_string::StaticState;
-use game::state::options::OptionsState;
-use engine::font::{FontCache, RenderableText};
-use super::resources;
-
-
-const FPS_LOCK: u32 = 1000 / 64;
+use engine::stack::StateStack;
+use engine::display::{DisplayCommand, DisplaySettings};
+use engine::debug::DebugOverlay;
+use engine::console::Console;
+use engine::watch::FileWatcher;
+use engine::sprite::Sprites;
+use engine::audio::{Audio, AudioCommand};
+use engine::options::Options;
+use engine::controller::{ControllerEvent, ControllerTranslator};
+use engine::error::{EngineError, OrLog};
+use engine::font::FontCache;
+use engine::render::RenderBackend;
+use engine::render::sdl::SdlRenderer;
+use engine::render::terminal::TerminalRenderer;
+use engine::terminal::{self as terminal_io, TerminalInput};
+use engine::capture::{self as frame_capture, Capture};
+
+
+/// Length of a single simulation step in milliseconds, every tick carries exactly this
+const SIMULATION_STEP: u32 = 10;
+/// Upper bound for the time a single frame may add to the simulation, so that long stalls
+/// (e.g. dragging the window) are not caught up with a burst of steps
+const MAX_FRAME_TIME: u32 = 250;
 
 /// Game Engine
 
 /// Holds all the data relevant to establishing the main game loop, to process SDL events
-/// (keyboard and mouse) etc.
-pub struct Engine {
-    pub engine_data: EngineData,
+/// (keyboard and mouse) etc. It is generic over the game's message type `M` and its
+/// settings `S`, the game itself is made of the states registered with `states`.
+pub struct Engine<M: MessageT, S> {
+    pub engine_data: EngineData<M, S>,
     pub context: SDL2Context,
     /// LinkedList for in-game messages
-    pub messages: VecDeque<Msg>,
+    pub messages: VecDeque<M>,
     pub event_pump: EventPump,
-    /// Renderer with static runtime since it corresponds to the window
-    pub renderer: Renderer<'static>,
+    /// Everything is drawn through this
+    pub renderer: Box<RenderBackend>,
     pub timer: TimerSubsystem,
     /// last update timestamp in SDL2 internal milliseconds
     pub last_update: u32,
-    pub states_stack: Vec<Box<StateT<EngineData = EngineData, Message = Msg>>>,
+    /// Time in milliseconds not yet consumed by simulation steps
+    accumulator: u32,
+    /// States of the game, along with the factories that create them
+    pub states: StateStack<M, EngineData<M, S>>,
     marked_events: HashSet<Keycode>,
+    /// Writes replayable messages down when recording
+    recorder: Option<Recorder>,
+    /// Replay being played back instead of SDL keyboard events
+    replay: Option<Replay<M, S>>,
+    /// Keyboard input when playing in a terminal
+    terminal_input: Option<TerminalInput>,
+    /// Screenshots and frame dumps
+    pub capture: Capture,
+    /// Fonts and other assets, by file name
+    pub resources: Resources,
+    /// Fonts loaded with `load_font`, as (key, file, size), loaded again when the file changes
+    fonts: Vec<(String, String, u16)>,
+    /// Sprites description loaded with `load_sprites`, and the sheets it refers to
+    sprites_file: Option<String>,
+    sprite_sheets: Vec<String>,
+    /// Resources and settings files, reloaded when they change
+    watcher: FileWatcher,
+    /// File with the tuned game settings, read at start and whenever it changes
+    pub game_config: PathBuf,
+    /// File the key bindings, volume and display settings are loaded from and saved to
+    pub options_config: PathBuf,
+    /// Drawn over the states while `EngineData::debug` is enabled
+    debug_overlay: DebugOverlay,
+    /// Developer console, opened with the backquote key
+    pub console: Console,
+    /// Plays the sound effects
+    pub audio: Audio,
+    /// Makes the samples played for the game's sound messages, set by the game
+    pub sounds: Option<fn(M::Sound) -> Vec<f32>>,
+    /// Opens the game controllers, `None` if SDL has no controller support
+    controller_subsystem: Option<GameControllerSubsystem>,
+    /// Controllers plugged in, they are only reported while open
+    controllers: Vec<GameController>,
+    /// Turns controller events into messages
+    controller_translator: ControllerTranslator,
 }
 
 /// Basic trait for all game engines.
@@ -63,328 +144,742 @@ pub trait TEngine {
     fn process(&mut self) -> bool;
 }
 
-impl Engine {
-    pub fn new(sdl_context: SDL2Context, ttf_context: Sdl2TtfContext) -> Engine {
-        let event_pump: EventPump = sdl_context.sdl2.event_pump().unwrap();
-        let video_subsystem: VideoSubsystem = sdl_context.sdl2.video().unwrap();
-        let mut timer: TimerSubsystem = sdl_context.sdl2.timer().unwrap();
-        let font_cache = FontCache::new(ttf_context);
-        let mut engine_data = EngineData::new(font_cache);
-        let window: Window = video_subsystem.window("SDL2 game",
-                    engine_data.window_size.0,
-                    engine_data.window_size.1)
-            .position_centered()
-            .resizable()
-            .opengl()
-            .allow_highdpi()
-            .build()
-            .expect("Could not create window!");
-
-        let mut renderer: Renderer<'static> = window.renderer()
-            .accelerated()
-            .build()
-            .expect("Could not aquire renderer");
-
-        engine_data.font_cache.load_font(&mut renderer,
-                                         "default",
-                                         resources::get_resource_path("PressStart2P-Regular.ttf"),
-                                         14);
-        engine_data.font_cache.load_font(&mut renderer,
-                                         "default-large",
-                                         resources::get_resource_path("PressStart2P-Regular.ttf"),
-                                         24);
-
-        renderer.set_logical_size(engine_data.window_size.0, engine_data.window_size.1)
-            .expect("Could not set logical size of renderer!");
-
-        let ticks = timer.ticks();
-
-        Engine {
-            engine_data: engine_data,
-            context: sdl_context,
-            messages: VecDeque::new(),
-            event_pump: event_pump,
-            renderer: renderer,
-            timer: timer,
-            last_update: ticks,
-            states_stack: vec![],
-            marked_events: HashSet::new(),
-        }
+impl<M: MessageT, S: SettingsT> Engine<M, S> {
+    pub fn new(sdl_context: SDL2Context,
+               ttf_context: Sdl2TtfContext)
+               -> Result<Engine<M, S>, EngineError> {
+        Engine::sdl(sdl_context, ttf_context, false)
     }
 
-    fn in_game_menu(&mut self) -> Box<MenuState> {
-        let choices = vec![("Resume", Msg::MenuCommand(MenuMsg::ResumeGame)),
-                           ("To Main Menu", Msg::MenuCommand(MenuMsg::ToMainMenu))];
-
-        Box::new(MenuState::new(&mut self.renderer,
-                                &mut self.engine_data.font_cache,
-                                choices,
-                                Some(Msg::MenuCommand(MenuMsg::ResumeGame)),
-                                MenuPosition::Centered,
-                                Some("PAUSE"),
-                                false))
+    /// Same as `new`, but uses SDL's software renderer, which also works with the dummy
+    /// video driver, so the game can be rendered (and captured) without a display
+    pub fn software(sdl_context: SDL2Context,
+                    ttf_context: Sdl2TtfContext)
+                    -> Result<Engine<M, S>, EngineError> {
+        Engine::sdl(sdl_context, ttf_context, true)
     }
 
-    fn main_menu(&mut self) -> Box<MenuState> {
-        let choices = vec![("New Game", Msg::StartGame),
-                           ("Controls", Msg::ShowOptions),
-                           ("Credits", Msg::ShowCredits),
-                           ("Exit Game", Msg::Exit)];
-
-        Box::new(MenuState::new(&mut self.renderer,
-                                &mut self.engine_data.font_cache,
-                                choices,
-                                None,
-                                MenuPosition::Centered,
-                                Some("HUNGRY PIXEL"),
-                                true))
+    fn sdl(sdl_context: SDL2Context,
+           ttf_context: Sdl2TtfContext,
+           software: bool)
+           -> Result<Engine<M, S>, EngineError> {
+        let video_subsystem: VideoSubsystem = sdl_context.sdl2
+            .video()
+            .map_err(EngineError::Init)?;
+        let engine_data: EngineData<M, S> = EngineData::new();
+        let mut window_builder = video_subsystem.window("SDL2 game",
+                                                        engine_data.window_size.0,
+                                                        engine_data.window_size.1);
+        window_builder.position_centered().resizable().allow_highdpi();
+        if !software {
+            window_builder.opengl();
+        }
+        let window: Window = window_builder.build()
+            .map_err(|e| EngineError::Window(e.to_string()))?;
+
+        let mut renderer_builder = window.renderer();
+        if software {
+            renderer_builder = renderer_builder.software();
+        } else {
+            renderer_builder = renderer_builder.accelerated();
+        }
+        let renderer: Renderer<'static> = renderer_builder.build()
+            .map_err(|e| EngineError::Window(format!("no renderer available, {}", e)))?;
+
+        let sdl_renderer = SdlRenderer::new(renderer,
+                                            FontCache::new(ttf_context),
+                                            engine_data.window_size);
+        let mut engine =
+            Engine::with_renderer(sdl_context, engine_data, Box::new(sdl_renderer))?;
+        // loaded along with the options
+        engine.apply_display_settings();
+        Ok(engine)
     }
 
-    fn intro_screen(&mut self) -> Box<StaticState> {
-        self.engine_data
-            .font_cache
-            .render_texture(&mut self.renderer,
-                            "default",
-                            "This is a game about a pixel who is very hungry.")
-            .unwrap();
-        let textures: Vec<Texture> =
-            vec![self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer,
-                                     "default",
-                                     "This is a game about a pixel who is very hungry.")
-                     .unwrap(),
-                 self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer, "default", "So he eats...")
-                     .unwrap(),
-                 self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer, "default", "And eats...")
-                     .unwrap(),
-                 self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer,
-                                     "default",
-                                     "He eats so much that he grows into a square!..")
-                     .unwrap()];
-        Box::new(StaticState::new(textures, 1000, Msg::MenuCommand(MenuMsg::ToMainMenu)))
+    /// Reads the tuned game settings, if there are any
+    pub fn load_game_settings(&mut self) {
+        if !self.game_config.is_file() {
+            return;
+        }
+        match S::load(&self.game_config) {
+            Ok(settings) => {
+                info!(Category::Engine,
+                      "Loaded game settings from {}",
+                      self.game_config.display());
+                self.engine_data.game_settings = settings;
+            }
+            Err(e) => {
+                error!(Category::Engine,
+                       "Could not load game settings from {}: {}",
+                       self.game_config.display(),
+                       e)
+            }
+        }
     }
 
-    fn gameover_screen(&mut self) -> Box<StaticState> {
-        let textures: Vec<Texture> =
-            vec![self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer, "default-large", "GAME OVER")
-                     .unwrap(),
-                 self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer, "default-large", "Unfortunately.")
-                     .unwrap()];
-        Box::new(StaticState::new(textures, 1000, Msg::MenuCommand(MenuMsg::ToMainMenu)))
+    /// Reads the key bindings, volume and display settings saved by a previous run, if any.
+    /// A file that can not be read is reported and the options stay as they are, the
+    /// defaults at start. The display settings are left for the caller to apply.
+    pub fn load_options(&mut self) {
+        if !self.options_config.is_file() {
+            return;
+        }
+        match Options::<M::Action>::load(&self.options_config) {
+            Ok(options) => {
+                info!(Category::Engine, "Loaded options from {}", self.options_config.display());
+                self.engine_data.input = options.input;
+                self.engine_data.audio = options.audio;
+                self.engine_data.display = options.display;
+                self.audio.set_settings(options.audio);
+            }
+            Err(e) => {
+                warn!(Category::Engine,
+                      "Could not load options from {}, keeping the current ones: {}",
+                      self.options_config.display(),
+                      e)
+            }
+        }
     }
 
-    fn winning_screen(&mut self) -> Box<StaticState> {
-        let textures: Vec<Texture> =
-            vec![self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer,
-                                     "default-large",
-                                     "Congratulations!")
-                     .unwrap(),
-                 self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer, "default-large", "You've won!")
-                     .unwrap()];
-        Box::new(StaticState::new(textures, 1000, Msg::ShowCredits))
+    /// Saves the key bindings, volume and display settings, unless a replay is playing
+    pub fn save_options(&mut self) {
+        // replays go through the options menu with the recorded keys, which must not end up
+        // in the player's options file; the changes still apply until the game is quit
+        if self.replay.is_some() {
+            info!(Category::Engine, "Options are not saved while replaying");
+            return;
+        }
+        let options = Options {
+            input: self.engine_data.input.clone(),
+            audio: self.engine_data.audio,
+            display: self.engine_data.display,
+        };
+        if let Err(e) = options.save(&self.options_config) {
+            error!(Category::Engine,
+                   "Could not save options to {}: {}",
+                   self.options_config.display(),
+                   e);
+        }
+        // the engine's own changes do not need reloading
+        self.watcher.watch(self.options_config.clone());
     }
 
-    fn credits(&mut self) -> Box<StaticState> {
-        let textures: Vec<Texture> =
-            vec![self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer, "default-large", "Author:")
-                     .unwrap(),
-                 self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer, "default-large", "Crazy-Owl")
-                     .unwrap(),
-                 self.engine_data
-                     .font_cache
-                     .render_texture(&mut self.renderer,
-                                     "default-large",
-                                     RenderableText::with_color("http://GitHub.com/Crazy-Owl", (0, 255, 0, 0)))
-                     .unwrap()];
-        Box::new(StaticState::new(textures, 1500, Msg::MenuCommand(MenuMsg::ToMainMenu)))
+    /// Moves the display settings older versions kept in `display.cfg` into the options
+    /// file. The old file is renamed, so that this only happens once.
+    fn migrate_display_settings(&mut self) {
+        let legacy = resources::get_config_path("display.cfg");
+        if !legacy.is_file() {
+            return;
+        }
+        match DisplaySettings::load_legacy(&legacy) {
+            Ok(display) => {
+                info!(Category::Engine,
+                      "Moving the display settings from {} to {}",
+                      legacy.display(),
+                      self.options_config.display());
+                self.engine_data.display = display;
+                self.save_options();
+                if let Err(e) = fs::rename(&legacy, legacy.with_extension("cfg.old")) {
+                    warn!(Category::Engine, "Could not rename {}: {}", legacy.display(), e);
+                }
+            }
+            Err(e) => warn!(Category::Engine, "Could not read {}: {}", legacy.display(), e),
+        }
     }
 
-    fn options(&mut self) -> Box<OptionsState> {
-        Box::new(OptionsState::new(&mut self.engine_data.font_cache, &mut self.renderer))
+    /// Loads the font resource `file` and makes it available to the states under `key`. The
+    /// font is loaded again whenever the file changes.
+    pub fn load_font(&mut self, key: &str, file: &str, size: u16) -> Result<(), EngineError> {
+        let data = self.resources.load(file)?;
+        self.renderer.load_font(key, &data, size)?;
+        for path in self.resources.candidates(file) {
+            self.watcher.watch(path);
+        }
+        self.fonts.retain(|&(ref loaded, _, _)| loaded != key);
+        self.fonts.push((key.to_string(), file.to_string(), size));
+        Ok(())
     }
 
-    pub fn start_game(&mut self) {
-        let intro_screen = self.intro_screen();
-        self.states_stack.push(intro_screen);
+    /// Loads the sprites described in the resource `file` (see `Sprites`) into
+    /// `EngineData::sprites`, along with their sheets. Sprites whose sheet can not be loaded
+    /// are left out, so that the entities are drawn with solid colors instead.
+    pub fn load_sprites(&mut self, file: &str) -> Result<(), EngineError> {
+        self.sprites_file = Some(file.to_string());
+        for path in self.resources.candidates(file) {
+            self.watcher.watch(path);
+        }
+        let data = self.resources.load(file)?;
+        let sprites = Sprites::parse(&String::from_utf8_lossy(&data))
+            .map_err(|e| EngineError::Texture(file.to_string(), e))?;
+
+        self.engine_data.sprites.clear();
+        self.sprite_sheets.clear();
+        let mut failed: Vec<String> = Vec::new();
+        for (name, sprite) in sprites {
+            if !self.sprite_sheets.contains(&sprite.sheet) && !failed.contains(&sprite.sheet) {
+                match self.load_sprite_sheet(&sprite.sheet) {
+                    Ok(()) => self.sprite_sheets.push(sprite.sheet.clone()),
+                    Err(e) => {
+                        warn!(Category::Engine, "{}, drawing with solid colors instead", e);
+                        failed.push(sprite.sheet.clone());
+                    }
+                }
+            }
+            if self.sprite_sheets.contains(&sprite.sheet) {
+                self.engine_data.sprites.insert(name, sprite);
+            }
+        }
+        // sheets that failed are watched too, so that fixing them brings the sprites back
+        self.sprite_sheets.extend(failed);
+        info!(Category::Engine, "Loaded {} sprites", self.engine_data.sprites.len());
+        Ok(())
     }
-}
 
-impl TEngine for Engine {
-    type Message = Msg;
-    type EngineData = EngineData;
+    fn load_sprite_sheet(&mut self, sheet: &str) -> Result<(), EngineError> {
+        for path in self.resources.candidates(sheet) {
+            self.watcher.watch(path);
+        }
+        let data = self.resources.load(sheet)?;
+        self.renderer.load_texture(sheet, &data)
+    }
 
-    fn update(&mut self, msg: Msg) -> Option<Msg> {
-        let mut current_msg = Some(msg);
-        'stack_propagation: for index in (0..self.states_stack.len()).rev() {
-            match (&mut self.states_stack[index])
-                .process_message(&mut self.engine_data, current_msg.unwrap()) {
-                None => {
-                    current_msg = None;
-                    break 'stack_propagation;
+    /// Reloads the resources and settings files that changed on disk, then lets the states
+    /// know. Game settings are left alone while recording or replaying, since the replay
+    /// holds the settings it was recorded with; so are the options, and while a key is being
+    /// bound too, which loading them would interrupt.
+    fn reload_changed_files(&mut self, now: u32) {
+        let changed = self.watcher.poll(now);
+        if changed.is_empty() {
+            return;
+        }
+        let mut reloaded_files = Vec::new();
+        for path in changed {
+            info!(Category::Engine, "{} changed", path.display());
+            if path == self.options_config {
+                if self.replay.is_some() || self.recorder.is_some() {
+                    warn!(Category::Engine,
+                          "Options are not reloaded while recording or replaying");
+                } else if self.engine_data.input.listening {
+                    warn!(Category::Engine, "Options are not reloaded while binding a key");
+                } else {
+                    let display = self.engine_data.display;
+                    self.load_options();
+                    if self.engine_data.display != display {
+                        self.apply_display_settings();
+                    }
+                }
+            } else if path == self.game_config {
+                if self.replay.is_some() || self.recorder.is_some() {
+                    warn!(Category::Engine,
+                          "Game settings are not reloaded while recording or replaying");
+                } else {
+                    self.load_game_settings();
                 }
-                Some(message) => {
-                    current_msg = Some(message);
-                    continue 'stack_propagation;
+            } else if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                if !reloaded_files.iter().any(|file: &String| file == name) {
+                    reloaded_files.push(name.to_string());
                 }
             }
         }
-        match current_msg {
-            Some(Msg::StartGame) => {
-                let game_state = GameState::new();
-                self.states_stack.push(Box::new(game_state));
-                None
+        let mut reload_sprites = false;
+        for file in reloaded_files {
+            self.resources.forget(&file);
+            if self.sprites_file.as_ref() == Some(&file) || self.sprite_sheets.contains(&file) {
+                reload_sprites = true;
             }
-            Some(Msg::MenuCommand(MenuMsg::ToMainMenu)) => {
-                let menu = self.main_menu();
-                let drain_range = ..self.states_stack.len();
-                self.states_stack.drain(drain_range);
-                self.states_stack.push(menu);
-                None
+            let fonts: Vec<(String, String, u16)> =
+                self.fonts.iter().filter(|font| font.1 == file).cloned().collect();
+            for (key, _, size) in fonts {
+                if let Err(e) = self.load_font(&key, &file, size) {
+                    error!(Category::Font, "Could not reload {}: {}", key, e);
+                }
             }
-            Some(Msg::MenuCommand(MenuMsg::ShowGameMenu)) => {
-                let menu = self.in_game_menu();
-                self.states_stack.push(menu);
-                None
+        }
+        if reload_sprites {
+            if let Some(file) = self.sprites_file.clone() {
+                if let Err(e) = self.load_sprites(&file) {
+                    error!(Category::Engine, "Could not reload sprites: {}", e);
+                }
+            }
+        }
+        self.states.reload(&mut self.engine_data);
+    }
+
+    fn apply_display_settings(&mut self) {
+        let display = self.engine_data.display;
+        let mode = if display.fullscreen {
+            Some(display.fullscreen_mode)
+        } else {
+            None
+        };
+        if let Err(e) = self.renderer.set_fullscreen(mode) {
+            error!(Category::Engine, "Could not change fullscreen mode: {}", e);
+        }
+        self.renderer.set_scaling(display.scaling);
+    }
+
+    fn process_display_command(&mut self, command: DisplayCommand) {
+        match command {
+            DisplayCommand::ToggleFullscreen => {
+                self.engine_data.display.fullscreen = !self.engine_data.display.fullscreen
             }
-            Some(Msg::PopState(x)) => {
-                for _ in 0..x {
-                    self.states_stack.pop();
+            DisplayCommand::NextFullscreenMode => self.engine_data.display.next_fullscreen_mode(),
+            DisplayCommand::NextScaling => self.engine_data.display.next_scaling(),
+            DisplayCommand::TogglePauseOnFocusLoss => {
+                self.engine_data.display.pause_on_focus_loss =
+                    !self.engine_data.display.pause_on_focus_loss
+            }
+        }
+        self.apply_display_settings();
+        self.save_options();
+    }
+
+    fn play_sound(&mut self, sound: M::Sound) {
+        match self.sounds {
+            Some(make) if self.audio.is_open() => self.audio.play(&Arc::new(make(sound))),
+            _ => trace!(Category::Engine, "Not playing {:?}", sound),
+        }
+    }
+
+    fn process_audio_command(&mut self, command: AudioCommand) {
+        self.engine_data.audio.apply(command);
+        let settings = self.engine_data.audio;
+        self.audio.set_settings(settings);
+        info!(Category::Engine,
+              "Volume {}{}",
+              settings.volume,
+              if settings.muted { " (muted)" } else { "" });
+        self.save_options();
+    }
+
+    /// Opens controllers as they are plugged in and forgets them when they are unplugged;
+    /// their input is ignored while replaying, and presses are while typing in the console,
+    /// like the keyboard's: releases still get through so nothing stays held
+    fn process_controller_event(&mut self, event: ControllerEvent, replaying: bool) {
+        match event {
+            ControllerEvent::Added(index) => {
+                if let Some(ref subsystem) = self.controller_subsystem {
+                    match subsystem.open(index) {
+                        Ok(controller) => {
+                            info!(Category::Input, "Controller connected: {}", controller.name());
+                            self.controllers.push(controller);
+                        }
+                        Err(e) => {
+                            warn!(Category::Input, "Could not open controller {}: {:?}", index, e)
+                        }
+                    }
                 }
-                None
             }
-            Some(Msg::ShowGameOver) => {
-                let drain_range = ..self.states_stack.len();
-                self.states_stack.drain(drain_range);
-                let gameover_screen = self.gameover_screen();
-                self.states_stack.push(gameover_screen);
-                None
+            ControllerEvent::Removed(_) => {
+                info!(Category::Input, "Controller disconnected");
+                self.controllers.retain(|controller| controller.attached());
             }
-            Some(Msg::ShowOptions) => {
-                let options = self.options();
-                self.states_stack.push(options);
-                None
+            ControllerEvent::Pressed(_) |
+            ControllerEvent::Released(_) if replaying => {}
+            ControllerEvent::Pressed(_) if self.console.open => {}
+            ControllerEvent::Pressed(input) => {
+                self.messages.push_back(M::from_event(EngineEvent::ControllerPressed(input)))
             }
-            Some(Msg::MenuCommand(MenuMsg::ResumeGame)) => {
-                self.states_stack.pop();
-                None
+            ControllerEvent::Released(input) => {
+                self.messages.push_back(M::from_event(EngineEvent::ControllerReleased(input)))
             }
-            Some(Msg::Exit) => {
-                self.engine_data.running = false;
+        }
+    }
+
+    /// Creates an engine that draws to the terminal with ANSI colors and reads the keyboard
+    /// from it, no window is opened
+    pub fn terminal(sdl_context: SDL2Context) -> Result<Engine<M, S>, EngineError> {
+        let engine_data: EngineData<M, S> = EngineData::new();
+        let renderer = TerminalRenderer::new(engine_data.window_size, terminal_io::terminal_size());
+        let mut engine = Engine::with_renderer(sdl_context, engine_data, Box::new(renderer))?;
+        engine.terminal_input = Some(TerminalInput::new());
+        Ok(engine)
+    }
+
+    fn with_renderer(sdl_context: SDL2Context,
+                     engine_data: EngineData<M, S>,
+                     renderer: Box<RenderBackend>)
+                     -> Result<Engine<M, S>, EngineError> {
+        let event_pump: EventPump = sdl_context.sdl2.event_pump().map_err(EngineError::Init)?;
+        let mut timer: TimerSubsystem = sdl_context.sdl2.timer().map_err(EngineError::Init)?;
+        let ticks = timer.ticks();
+        let game_config = resources::get_config_path("game.cfg");
+        let options_config = resources::get_config_path("options.cfg");
+        let mut watcher = FileWatcher::new();
+        watcher.watch(game_config.clone());
+        watcher.watch(options_config.clone());
+        let audio = Audio::new(&sdl_context.sdl2, engine_data.audio);
+        // controllers plugged in already are reported as added by the first events
+        let controller_subsystem = match sdl_context.sdl2.game_controller() {
+            Ok(subsystem) => Some(subsystem),
+            Err(e) => {
+                warn!(Category::Input, "Game controllers are not available: {}", e);
                 None
             }
-            Some(Msg::ShowWinScreen) => {
-                let drain_range = ..self.states_stack.len();
-                self.states_stack.drain(drain_range);
-                let win_screen = self.winning_screen();
-                self.states_stack.push(win_screen);
-                None
+        };
+
+        let mut engine = Engine {
+            engine_data: engine_data,
+            context: sdl_context,
+            messages: VecDeque::new(),
+            event_pump: event_pump,
+            renderer: renderer,
+            timer: timer,
+            last_update: ticks,
+            accumulator: 0,
+            states: StateStack::new(),
+            marked_events: HashSet::new(),
+            recorder: None,
+            replay: None,
+            terminal_input: None,
+            capture: Capture::new(),
+            resources: Resources::new(),
+            fonts: Vec::new(),
+            sprites_file: None,
+            sprite_sheets: Vec::new(),
+            watcher: watcher,
+            game_config: game_config,
+            options_config: options_config,
+            debug_overlay: DebugOverlay,
+            console: Console::new(),
+            audio: audio,
+            sounds: None,
+            controller_subsystem: controller_subsystem,
+            controllers: Vec::new(),
+            controller_translator: ControllerTranslator::new(),
+        };
+        engine.load_game_settings();
+        engine.load_options();
+        engine.migrate_display_settings();
+        Ok(engine)
+    }
+
+    /// Starts writing every tick and button event that reaches the states stack to `path`.
+    /// The seed is fixed for the whole session so the recording can be played back.
+    pub fn start_recording<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let seed = match self.engine_data.seed {
+            Some(seed) => seed,
+            None => rand::random(),
+        };
+        self.engine_data.seed = Some(seed);
+        self.recorder = Some(Recorder::create(path, seed, &self.engine_data.game_settings)?);
+        Ok(())
+    }
+
+    /// Feeds the replay through `update` instead of keyboard events, using its seed and settings
+    pub fn start_playback(&mut self, replay: Replay<M, S>) {
+        self.engine_data.seed = Some(replay.seed);
+        self.engine_data.game_settings = replay.settings.clone();
+        self.replay = Some(replay);
+    }
+
+    /// Starts or stops SDL text input events, which the console is typed with
+    fn set_text_input(&mut self, enabled: bool) {
+        match self.context.sdl2.video() {
+            Ok(video) => {
+                if enabled {
+                    video.text_input().start();
+                } else {
+                    video.text_input().stop();
+                }
             }
-            Some(Msg::ShowCredits) => {
-                let drain_range = ..self.states_stack.len();
-                self.states_stack.drain(drain_range);
-                let credits_screen = self.credits();
-                self.states_stack.push(credits_screen);
-                None
+            Err(e) => error!(Category::Input, "Could not switch text input: {}", e),
+        }
+    }
+
+    /// Runs a line entered in the console: `push <message>` queues a message (parsed with
+    /// the message type's `from_str`), anything else is offered to the states. `help` lists
+    /// `push` along with whatever the states answer to it.
+    pub fn run_console_command(&mut self, line: &str) {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        let output = match args.get(0).map(|w| *w) {
+            Some("help") => {
+                match self.states.console_command(&mut self.engine_data, &args) {
+                    Some(commands) => format!("push MESSAGE, {}", commands),
+                    None => "push MESSAGE".to_string(),
+                }
             }
-            Some(x) => {
-                self.messages.push_back(x);
-                None
+            Some("push") => {
+                match line.trim_start()[4..].parse::<M>() {
+                    Ok(msg) => {
+                        self.messages.push_back(msg);
+                        format!("Queued {:?}", msg)
+                    }
+                    Err(e) => e,
+                }
             }
-            _ => None,
+            _ => {
+                match self.states.console_command(&mut self.engine_data, &args) {
+                    Some(output) => output,
+                    None => format!("Unknown command: {}", line),
+                }
+            }
+        };
+        self.console.print(output);
+    }
+
+    /// Creates the state registered under `name` and puts it on top of the stack
+    pub fn push_state(&mut self, name: &str) {
+        if let Err(e) = self.states.push(name, &mut self.engine_data) {
+            error!(Category::State, "{}", e);
         }
     }
+}
 
-    fn render(&mut self) {
-        self.renderer.set_draw_color(RGB(0, 0, 0));
-        self.renderer.clear();
-        {
-            self.renderer.set_draw_color(RGB(150, 150, 150));
-            self.renderer
-                .draw_rect(Rect::new(0,
-                                     0,
-                                     self.engine_data.window_size.0,
-                                     self.engine_data.window_size.1))
-                .unwrap();
-        }
-        if !self.states_stack.is_empty() {
-            let mut last_drawable_index = self.states_stack.len();
-            'fullscreen: for index in (0..self.states_stack.len()).rev() {
-                if self.states_stack[index].is_fullscreen() {
-                    last_drawable_index = index;
-                    break 'fullscreen;
+impl<M: MessageT, S: SettingsT> TEngine for Engine<M, S> {
+    type Message = M;
+    type EngineData = EngineData<M, S>;
+
+    fn update(&mut self, msg: M) -> Option<M> {
+        // replays hold the actions, translated with the bindings of the player who recorded
+        // them, so their messages are not translated again
+        let msg = if self.replay.is_some() {
+            msg
+        } else {
+            self.engine_data.input.translate(msg)
+        };
+        let record_result = match self.recorder {
+            Some(ref mut recorder) => recorder.record(&msg),
+            None => Ok(()),
+        };
+        if let Err(e) = record_result {
+            error!(Category::Engine, "Could not write replay, recording stopped: {}", e);
+            self.recorder = None;
+        }
+
+        if let Some(EngineEvent::Tick(x)) = msg.event() {
+            let due = self.engine_data.scheduler.advance(x);
+            self.messages.extend(due);
+        }
+
+        trace!(Category::Engine, "Routing {:?}", msg);
+        let current_msg = self.states.propagate(&mut self.engine_data, msg);
+        if let Some(ref x) = current_msg {
+            trace!(Category::Engine, "{:?} fell through the stack", x);
+        }
+        self.messages.extend(self.engine_data.take_sent());
+        let current_msg = match current_msg {
+            Some(msg) => msg,
+            None => return None,
+        };
+        if let Some(sound) = current_msg.sound() {
+            self.play_sound(sound);
+            return None;
+        }
+        match current_msg.event() {
+            Some(EngineEvent::Stack(command)) => {
+                if let Err(e) = self.states.apply(command, &mut self.engine_data) {
+                    error!(Category::State, "{}", e);
                 }
             }
-            for index in last_drawable_index..self.states_stack.len() {
-                self.states_stack[index].render(&mut self.renderer, &mut self.engine_data);
+            Some(EngineEvent::Display(command)) => self.process_display_command(command),
+            Some(EngineEvent::Audio(command)) => self.process_audio_command(command),
+            Some(EngineEvent::OptionsChanged) => {
+                let settings = self.engine_data.audio;
+                self.audio.set_settings(settings);
+                self.save_options();
+            }
+            // states pass ticks on to the ones below, so every tick ends up here; queueing it
+            // again would have it processed forever
+            Some(EngineEvent::Tick(_)) => {}
+            // input nobody was interested in
+            Some(EngineEvent::ButtonPressed(_)) |
+            Some(EngineEvent::ButtonReleased(_)) |
+            Some(EngineEvent::ControllerPressed(_)) |
+            Some(EngineEvent::ControllerReleased(_)) |
+            Some(EngineEvent::ActionPressed(_)) |
+            Some(EngineEvent::ActionReleased(_)) |
+            Some(EngineEvent::FocusLost) |
+            Some(EngineEvent::FocusGained) |
+            Some(EngineEvent::TimerFired(_)) => {}
+            Some(EngineEvent::Exit) => self.engine_data.running = false,
+            // queueing it again would have it fall through again, forever
+            None => warn!(Category::Engine, "Nothing handled {:?}, dropped", current_msg),
+        }
+        None
+    }
+
+    fn render(&mut self) {
+        self.renderer.clear(RGB(0, 0, 0));
+        self.renderer
+            .draw_rect(Rect::new(0,
+                                 0,
+                                 self.engine_data.window_size.0,
+                                 self.engine_data.window_size.1),
+                       RGB(150, 150, 150))
+            .or_log();
+        self.states.render(&mut *self.renderer, &mut self.engine_data);
+        if self.engine_data.debug.enabled {
+            self.engine_data.debug.states = self.states.describe();
+            self.states.render_debug(&mut *self.renderer, &mut self.engine_data);
+            self.debug_overlay.render(&mut *self.renderer, &self.engine_data.debug);
+        }
+        if self.console.open {
+            self.console.render(&mut *self.renderer, self.engine_data.window_size);
+        }
+        if let Some(path) = self.capture.next_frame() {
+            let saved = self.renderer
+                .read_pixels()
+                .and_then(|frame| frame_capture::save_png(&frame, &path));
+            match saved {
+                Ok(()) => info!(Category::Engine, "Saved {}", path.display()),
+                Err(e) => error!(Category::Engine, "Could not save {}: {}", path.display(), e),
             }
         }
-        // if let Some(state) = self.states_stack.last_mut() {
-        //     state.render(&mut self.renderer, &self.engine_data);
-        // }
         self.renderer.present();
     }
 
     fn process(&mut self) -> bool {
-        let ticks_at_start = self.timer.ticks();
+        let now = self.timer.ticks();
+        self.engine_data.debug.record_frame(now - self.last_update);
+        self.accumulator += cmp::min(now - self.last_update, MAX_FRAME_TIME);
+        self.last_update = now;
 
         self.marked_events.drain();
+        let replaying = self.replay.is_some();
+        let pause_on_focus_loss = self.engine_data.display.pause_on_focus_loss;
+        // console commands are not part of replays, so it can not be used with them
+        let console_allowed = !replaying && self.recorder.is_none();
+        let console_was_open = self.console.open;
+        let mut console_lines = Vec::new();
+        let mut controller_events = Vec::new();
 
         for event in self.event_pump.poll_iter() {
             use sdl2::event::Event::*;
             use sdl2::event::WindowEvent;
 
-            if let KeyDown { keycode: x, .. } = event {
-                if self.marked_events.contains(&(x.unwrap())) {
+            // keys SDL has no keycode for are let through, nothing handles them anyway
+            if let KeyDown { keycode: Some(x), .. } = event {
+                if self.marked_events.contains(&x) {
                     continue;
                 } else {
-                    self.marked_events.insert(x.unwrap());
+                    self.marked_events.insert(x);
                 }
             }
 
+            controller_events.extend(self.controller_translator.translate(&event));
+
             match event {
-                Quit { .. } => self.messages.push_back(Msg::Exit),
+                Quit { .. } => self.messages.push_back(M::from_event(EngineEvent::Exit)),
+                KeyDown { keycode: Some(Keycode::Backquote), .. } => {
+                    if console_allowed {
+                        self.console.toggle();
+                    } else {
+                        warn!(Category::Input,
+                              "The console is not available while recording or replaying");
+                    }
+                }
+                KeyDown { keycode: Some(x), .. } if self.console.open => {
+                    if let Some(line) = self.console.key(x) {
+                        console_lines.push(line);
+                    }
+                }
+                TextInput { ref text, .. } if self.console.open => self.console.type_text(text),
+                KeyDown { keycode: Some(Keycode::F12), .. } => self.capture.request_screenshot(),
+                KeyUp { keycode: Some(Keycode::F12), .. } => {}
+                KeyDown { keycode: Some(Keycode::F11), .. } => {
+                    let toggle = EngineEvent::Display(DisplayCommand::ToggleFullscreen);
+                    self.messages.push_back(M::from_event(toggle));
+                }
+                KeyUp { keycode: Some(Keycode::F11), .. } => {}
+                KeyDown { keycode: Some(Keycode::F3), .. } => {
+                    self.engine_data.debug.enabled = !self.engine_data.debug.enabled;
+                }
+                KeyUp { keycode: Some(Keycode::F3), .. } => {}
+                KeyDown { keycode: Some(Keycode::F6), .. } => {
+                    let volume = EngineEvent::Audio(AudioCommand::VolumeDown);
+                    self.messages.push_back(M::from_event(volume));
+                }
+                KeyDown { keycode: Some(Keycode::F7), .. } => {
+                    let volume = EngineEvent::Audio(AudioCommand::VolumeUp);
+                    self.messages.push_back(M::from_event(volume));
+                }
+                KeyDown { keycode: Some(Keycode::F8), .. } => {
+                    let mute = EngineEvent::Audio(AudioCommand::ToggleMute);
+                    self.messages.push_back(M::from_event(mute));
+                }
+                KeyUp { keycode: Some(Keycode::F6), .. } |
+                KeyUp { keycode: Some(Keycode::F7), .. } |
+                KeyUp { keycode: Some(Keycode::F8), .. } => {}
+                KeyDown { .. } | KeyUp { .. } if replaying => {}
                 KeyDown { keycode: Some(x), .. } => {
-                    self.messages.push_back(Msg::ButtonPressed(x));
+                    trace!(Category::Input, "{} pressed", x.name());
+                    self.messages.push_back(M::from_event(EngineEvent::ButtonPressed(x)));
                 }
                 KeyUp { keycode: Some(x), .. } => {
-                    self.messages.push_back(Msg::ButtonReleased(x));
+                    trace!(Category::Input, "{} released", x.name());
+                    self.messages.push_back(M::from_event(EngineEvent::ButtonReleased(x)));
+                }
+                // focus changes are part of replays, like keys
+                Window { win_event: WindowEvent::FocusLost, .. } |
+                Window { win_event: WindowEvent::Minimized, .. } if pause_on_focus_loss &&
+                                                                    !replaying => {
+                    self.messages.push_back(M::from_event(EngineEvent::FocusLost));
                 }
-                Window { win_event: WindowEvent::Resized(x, y), .. } => {
-                    println!("Window resized, {} {}", x, y);
+                Window { win_event: WindowEvent::FocusGained, .. } |
+                Window { win_event: WindowEvent::Restored, .. } if pause_on_focus_loss &&
+                                                                   !replaying => {
+                    self.messages.push_back(M::from_event(EngineEvent::FocusGained));
+                }
+                Window { win_event: WindowEvent::SizeChanged(width, height), .. } => {
+                    debug!(Category::Engine, "Window resized to {}x{}", width, height);
+                    self.renderer.set_scaling(self.engine_data.display.scaling);
                 }
                 _ => {}
             }
         }
 
-        self.render();
-        while let Some(msg) = self.messages.pop_front() {
-            self.update(msg).map(|m| self.messages.push_back(m));
+        let console_open = self.console.open;
+        if console_open != console_was_open {
+            self.set_text_input(console_open);
+        }
+        for line in console_lines {
+            self.run_console_command(&line);
+        }
+        for event in controller_events {
+            self.process_controller_event(event, replaying);
+        }
+        self.reload_changed_files(now);
+
+        if let Some(ref mut input) = self.terminal_input {
+            for event in input.poll::<M::Action>(now) {
+                match event {
+                    EngineEvent::ButtonPressed(_) |
+                    EngineEvent::ButtonReleased(_) if replaying => {}
+                    event => self.messages.push_back(M::from_event(event)),
+                }
+            }
         }
 
-        let ticks_at_finish = self.timer.ticks();
-        if ticks_at_finish - ticks_at_start < FPS_LOCK {
-            self.timer.delay(FPS_LOCK - (ticks_at_finish - ticks_at_start));
+        // Input gathered so far is processed together with the next simulation step, so that
+        // the order of messages does not depend on the frame rate
+        while self.accumulator >= SIMULATION_STEP {
+            let replay_step = match self.replay {
+                Some(ref mut replay) => replay.next_step(),
+                None => None,
+            };
+            match replay_step {
+                Some(step) => self.messages.extend(step),
+                None => {
+                    if self.replay.is_some() {
+                        info!(Category::Engine, "Replay finished");
+                        self.replay = None;
+                    }
+                    self.messages.push_back(M::from_event(EngineEvent::Tick(SIMULATION_STEP)));
+                }
+            }
+            self.engine_data.debug.queue_len = self.messages.len();
+            while let Some(msg) = self.messages.pop_front() {
+                self.update(msg).map(|m| self.messages.push_back(m));
+            }
+            self.accumulator -= SIMULATION_STEP;
+        }
+
+        self.render();
+
+        if self.accumulator < SIMULATION_STEP {
+            self.timer.delay(SIMULATION_STEP - self.accumulator);
         }
-        self.messages.push_back(Msg::Tick(self.timer.ticks() - ticks_at_start));
         self.engine_data.running
     }
 }
@@ -0,0 +1,183 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use engine::audio::{AudioSettings, MAX_VOLUME};
+use engine::display::{parse_switch, DisplaySettings};
+use engine::input::{ActionT, Input, InputMap};
+
+/// Version of the options file format, written on its first line
+pub const OPTIONS_VERSION: u32 = 2;
+
+/// Player options that are kept between runs: the key bindings, the volume and the window
+/// settings. They are saved as text, a `version` line followed by one `setting value` line
+/// per setting and one `bind action input` line per binding, e.g. `bind up key W` or
+/// `bind up button dpup` (see `Input::config_name`).
+#[derive(Debug, Clone)]
+pub struct Options<A> {
+    pub input: InputMap<A>,
+    pub audio: AudioSettings,
+    pub display: DisplaySettings,
+}
+
+impl<A: ActionT> Options<A> {
+    pub fn new() -> Options<A> {
+        Options {
+            input: InputMap::new(),
+            audio: AudioSettings::new(),
+            display: DisplaySettings::new(),
+        }
+    }
+
+    /// Reads options written by `save`. Any line that can not be understood, a missing
+    /// version or one newer than `OPTIONS_VERSION` make the whole file invalid, rather than
+    /// leaving the player with half of the bindings. Actions the file has no binding for get
+    /// their default ones, so that the menus can always be used.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Options<A>, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| e.to_string())?;
+
+        let mut options = Options::new();
+        let mut input = InputMap::empty();
+        let mut version = None;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |e: String| format!("line {}: {}", index + 1, e);
+            let mut parts = line.splitn(2, ' ');
+            let (key, value) = (parts.next().unwrap_or(""), parts.next().unwrap_or("").trim());
+            if version.is_none() {
+                if key != "version" {
+                    return Err(error("expected the version".to_string()));
+                }
+                let number = value.parse::<u32>()
+                    .map_err(|_| error(format!("invalid version {}", value)))?;
+                if number > OPTIONS_VERSION {
+                    return Err(format!("written by a newer version ({})", number));
+                }
+                // version 1 had no window settings, they keep their defaults
+                version = Some(number);
+                continue;
+            }
+            match key {
+                "volume" => {
+                    let volume = value.parse::<u8>().unwrap_or(MAX_VOLUME + 1);
+                    if volume > MAX_VOLUME {
+                        return Err(error(format!("invalid volume {}", value)));
+                    }
+                    options.audio.volume = volume;
+                }
+                "muted" => options.audio.muted = parse_switch(value).map_err(&error)?,
+                "bind" => {
+                    let (action, bound) = parse_binding(value).map_err(&error)?;
+                    input.bind(bound, action);
+                }
+                _ => options.display.set(key, value).map_err(&error)?,
+            }
+        }
+        if version.is_none() {
+            return Err("the file is empty".to_string());
+        }
+
+        for action in A::all() {
+            if input.inputs(action).is_empty() {
+                for default in options.input.inputs(action) {
+                    if input.action(default).is_none() {
+                        input.bind(default, action);
+                    }
+                }
+            }
+        }
+        options.input = input;
+        Ok(options)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(path)?;
+        writeln!(file, "version {}", OPTIONS_VERSION)?;
+        writeln!(file, "volume {}", self.audio.volume)?;
+        writeln!(file, "muted {}", if self.audio.muted { "on" } else { "off" })?;
+        self.display.write(&mut file)?;
+        for action in A::all() {
+            for input in self.input.inputs(action) {
+                writeln!(file,
+                         "bind {} {}",
+                         action.to_string().to_lowercase(),
+                         input.config_name())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_binding<A: ActionT>(value: &str) -> Result<(A, Input), String> {
+    let mut parts = value.splitn(2, ' ');
+    let action = parts.next().unwrap_or("").parse::<A>()?;
+    let input = parts.next().unwrap_or("").parse::<Input>()?;
+    Ok((action, input))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sdl2::keyboard::Keycode;
+
+    use engine::display::ScalingPolicy;
+    use engine::input::Input;
+    use engine::message::testing::TestAction;
+    use testing::{temp_path, with_text_file};
+    use super::Options;
+
+    fn load_text(name: &str, text: &str) -> Result<Options<TestAction>, String> {
+        with_text_file(&format!("options-{}", name), text, |path| Options::load(path))
+    }
+
+    #[test]
+    fn saved_options_are_loaded_back() {
+        let path = temp_path("options-round-trip");
+        let mut options: Options<TestAction> = Options::new();
+        options.input.rebind(TestAction::Jump, Input::Key(Keycode::I));
+        options.audio.volume = 3;
+        options.audio.muted = true;
+        options.display.scaling = ScalingPolicy::IntegerScale;
+        options.display.pause_on_focus_loss = false;
+        options.save(&path).unwrap();
+        let loaded: Options<TestAction> = Options::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.input.inputs(TestAction::Jump),
+                   options.input.inputs(TestAction::Jump));
+        assert_eq!(loaded.input.action(Input::Key(Keycode::Space)), None);
+        assert_eq!(loaded.audio, options.audio);
+        assert_eq!(loaded.display, options.display);
+    }
+
+    #[test]
+    fn older_files_keep_the_default_display() {
+        let options = load_text("version-1", "version 1\nvolume 4\nbind jump key I\n").unwrap();
+        assert_eq!(options.audio.volume, 4);
+        assert_eq!(options.display, Options::<TestAction>::new().display);
+        assert_eq!(options.input.action(Input::Key(Keycode::I)),
+                   Some(TestAction::Jump));
+        // actions the file does not bind get their defaults
+        assert_eq!(options.input.action(Input::Key(Keycode::Escape)), Some(TestAction::Back));
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        for text in ["", "volume 4\n", "version 99\n", "version 2\nvolume 11\n",
+                     "version 2\nscaling sideways\n", "version 2\nbind fly key W\n",
+                     "version 2\nspeed 4\n"]
+            .iter() {
+            assert!(load_text("invalid", text).is_err(), "{:?} was accepted", text);
+        }
+    }
+}
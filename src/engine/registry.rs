@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use engine::state::StateT;
+
+/// Builds a new instance of a state
+pub type StateFactory<M, D> = Box<Fn(&mut D) -> Box<StateT<Message = M, EngineData = D>>>;
+
+/// State registry
+/// States are registered under a name together with a factory closure; stack messages refer
+/// to states by these names, so new screens can be added without touching the engine.
+pub struct StateRegistry<M, D> {
+    factories: HashMap<String, StateFactory<M, D>>,
+}
+
+impl<M, D> StateRegistry<M, D> {
+    pub fn new() -> StateRegistry<M, D> {
+        StateRegistry { factories: HashMap::new() }
+    }
+
+    /// Registers `factory` under `name`, replacing any previous registration
+    pub fn register<T, F>(&mut self, name: T, factory: F)
+        where T: Into<String>,
+              F: Fn(&mut D) -> Box<StateT<Message = M, EngineData = D>> + 'static
+    {
+        self.factories.insert(name.into(), Box::new(factory));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Creates a new instance of the state registered under `name`
+    pub fn create(&self,
+                  name: &str,
+                  data: &mut D)
+                  -> Option<Box<StateT<Message = M, EngineData = D>>> {
+        self.factories.get(name).map(|factory| (**factory)(data))
+    }
+}
@@ -0,0 +1,81 @@
+pub mod sdl;
+pub mod software;
+pub mod terminal;
+
+use std::cmp;
+
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+
+use engine::font::RenderableText;
+use engine::display::{FullscreenMode, ScalingPolicy};
+use engine::error::EngineError;
+
+/// Contents of the drawing area, 3 bytes (red, green, blue) per pixel, row by row
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Drawing interface
+/// States draw exclusively through this trait, so that they do not depend on any particular
+/// renderer: `sdl::SdlRenderer` draws to the window, `software::SoftwareRenderer` draws to
+/// memory and remembers what was drawn, `terminal::TerminalRenderer` draws with ANSI colors.
+pub trait RenderBackend {
+    /// Logical size of the drawing area
+    fn output_size(&self) -> (u32, u32);
+    /// Fills the whole drawing area with `color`
+    fn clear(&mut self, color: Color);
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), EngineError>;
+    /// Draws the outline of `rect`
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<(), EngineError>;
+    /// Copies the `src` part (or all) of a texture registered under `texture` into `dst`
+    fn blit(&mut self, texture: &str, src: Option<Rect>, dst: Rect) -> Result<(), EngineError>;
+    /// Draws a line between two points, by default with single pixel rectangles
+    fn draw_line(&mut self, from: Point, to: Point, color: Color) -> Result<(), EngineError> {
+        let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+        let steps = cmp::max(dx.abs(), dy.abs());
+        for step in 0..steps + 1 {
+            let (x, y) = if steps == 0 {
+                (from.x(), from.y())
+            } else {
+                (from.x() + dx * step / steps, from.y() + dy * step / steps)
+            };
+            self.fill_rect(Rect::new(x, y, 1, 1), color)?;
+        }
+        Ok(())
+    }
+    /// Draws text with the font registered under `font`, `(x, y)` is the upper left corner
+    fn draw_text(&mut self,
+                 font: &str,
+                 text: &RenderableText,
+                 x: i32,
+                 y: i32)
+                 -> Result<(), EngineError>;
+    /// Dimensions `text` would take if drawn with `font`
+    fn text_size(&self, font: &str, text: &str) -> Result<(u32, u32), EngineError>;
+    /// Makes the TTF font in `data` (the contents of a font file) available under `key`;
+    /// renderers that do not draw with TTF fonts ignore it
+    fn load_font(&mut self, _key: &str, _data: &[u8], _size: u16) -> Result<(), EngineError> {
+        Ok(())
+    }
+    /// Makes the PNG image in `data` available to `blit` under `key`
+    fn load_texture(&mut self, key: &str, _data: &[u8]) -> Result<(), EngineError> {
+        Err(EngineError::Texture(key.to_string(),
+                                 "This renderer can not draw textures".to_string()))
+    }
+    /// Fits the logical drawing area into the current output size according to `policy`;
+    /// has to be called again whenever the output size changes
+    fn set_scaling(&mut self, _policy: ScalingPolicy) {}
+    /// Switches to fullscreen with the given mode, or back to a window with `None`
+    fn set_fullscreen(&mut self, _mode: Option<FullscreenMode>) -> Result<(), EngineError> {
+        Err(EngineError::Window("This renderer has no window".to_string()))
+    }
+    /// Shows everything drawn since the last `present`
+    fn present(&mut self);
+    /// Reads back what has been drawn so far
+    fn read_pixels(&mut self) -> Result<Frame, EngineError> {
+        Err(EngineError::Render("This renderer can not capture frames".to_string()))
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use sdl2::image::ImageRWops;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Renderer, Texture};
+use sdl2::rwops::RWops;
+use sdl2::video::FullscreenType;
+
+use engine::font::{FontCache, RenderableText};
+use engine::render::{Frame, RenderBackend};
+use engine::display::{self, FullscreenMode, ScalingPolicy};
+use engine::error::EngineError;
+
+/// `RenderBackend` drawing to an SDL window
+pub struct SdlRenderer {
+    /// Renderer with static runtime since it corresponds to the window
+    pub renderer: Renderer<'static>,
+    /// Size of the drawing area states draw to, scaled to the window by `set_scaling`
+    logical_size: (u32, u32),
+    pub font_cache: FontCache,
+    /// Textures available to `blit`, by key
+    pub textures: HashMap<String, Texture>,
+}
+
+impl SdlRenderer {
+    pub fn new(renderer: Renderer<'static>,
+               font_cache: FontCache,
+               logical_size: (u32, u32))
+               -> SdlRenderer {
+        SdlRenderer {
+            renderer: renderer,
+            logical_size: logical_size,
+            font_cache: font_cache,
+            textures: HashMap::new(),
+        }
+    }
+}
+
+impl RenderBackend for SdlRenderer {
+    fn output_size(&self) -> (u32, u32) {
+        self.logical_size
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.renderer.set_draw_color(color);
+        self.renderer.clear();
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), EngineError> {
+        self.renderer.set_draw_color(color);
+        self.renderer.fill_rect(Some(rect)).map_err(EngineError::Render)
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<(), EngineError> {
+        self.renderer.set_draw_color(color);
+        self.renderer.draw_rect(rect).map_err(EngineError::Render)
+    }
+
+    fn blit(&mut self, texture: &str, src: Option<Rect>, dst: Rect) -> Result<(), EngineError> {
+        let texture = self.textures
+            .get(texture)
+            .ok_or_else(|| EngineError::MissingTexture(texture.to_string()))?;
+        self.renderer.copy(texture, src, Some(dst)).map_err(EngineError::Render)
+    }
+
+    fn draw_text(&mut self,
+                 font: &str,
+                 text: &RenderableText,
+                 x: i32,
+                 y: i32)
+                 -> Result<(), EngineError> {
+        self.font_cache.render_text(&mut self.renderer, font, text.clone(), x, y)
+    }
+
+    fn text_size(&self, font: &str, text: &str) -> Result<(u32, u32), EngineError> {
+        self.font_cache.text_size(font, text)
+    }
+
+    fn draw_line(&mut self, from: Point, to: Point, color: Color) -> Result<(), EngineError> {
+        self.renderer.set_draw_color(color);
+        self.renderer.draw_line(from, to).map_err(EngineError::Render)
+    }
+
+    fn load_font(&mut self, key: &str, data: &[u8], size: u16) -> Result<(), EngineError> {
+        self.font_cache.load_font(&mut self.renderer, key, data, size)
+    }
+
+    fn load_texture(&mut self, key: &str, data: &[u8]) -> Result<(), EngineError> {
+        let texture = {
+            let rwops = RWops::from_bytes(data)
+                .map_err(|e| EngineError::Texture(key.to_string(), e))?;
+            let surface = rwops.load_png().map_err(|e| EngineError::Texture(key.to_string(), e))?;
+            self.renderer
+                .create_texture_from_surface(&surface)
+                .map_err(|e| EngineError::Texture(key.to_string(), e.to_string()))?
+        };
+        self.textures.insert(key.to_string(), texture);
+        Ok(())
+    }
+
+    fn set_scaling(&mut self, policy: ScalingPolicy) {
+        let output = match self.renderer.output_size() {
+            Ok(size) => size,
+            Err(_) => return,
+        };
+        let (rect, scale_x, scale_y) = display::viewport(policy, self.logical_size, output);
+        if self.renderer.set_scale(scale_x, scale_y).is_err() {
+            return;
+        }
+        // the viewport is scaled along with everything else
+        self.renderer.set_viewport(Some(Rect::new((rect.x() as f32 / scale_x) as i32,
+                                                  (rect.y() as f32 / scale_y) as i32,
+                                                  self.logical_size.0,
+                                                  self.logical_size.1)));
+    }
+
+    fn set_fullscreen(&mut self, mode: Option<FullscreenMode>) -> Result<(), EngineError> {
+        let fullscreen_type = match mode {
+            None => FullscreenType::Off,
+            Some(FullscreenMode::Desktop) => FullscreenType::Desktop,
+            Some(FullscreenMode::Exclusive) => FullscreenType::True,
+        };
+        match self.renderer.window_mut() {
+            Some(window) => window.set_fullscreen(fullscreen_type).map_err(EngineError::Window),
+            None => Err(EngineError::Window("The renderer does not draw to a window".to_string())),
+        }
+    }
+
+    fn present(&mut self) {
+        self.renderer.present();
+    }
+
+    fn read_pixels(&mut self) -> Result<Frame, EngineError> {
+        let (width, height) = self.renderer.output_size().map_err(EngineError::Render)?;
+        // with the viewport and scale of `set_scaling` SDL would read the scaled drawing area,
+        // not the `output_size` of the window, so they are taken off for the capture
+        let viewport = self.renderer.viewport();
+        let (scale_x, scale_y) = self.renderer.scale();
+        self.renderer.set_scale(1.0, 1.0).map_err(EngineError::Render)?;
+        self.renderer.set_viewport(None);
+        let pixels = self.renderer
+            .read_pixels(Some(Rect::new(0, 0, width, height)), PixelFormatEnum::RGB24);
+        self.renderer.set_scale(scale_x, scale_y).map_err(EngineError::Render)?;
+        self.renderer.set_viewport(Some(viewport));
+        let pixels = pixels.map_err(EngineError::Render)?;
+        Ok(Frame {
+            width: width,
+            height: height,
+            pixels: pixels,
+        })
+    }
+}
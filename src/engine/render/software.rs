@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use engine::font::{ColorMod, RenderableText};
+use engine::error::EngineError;
+use engine::render::{Frame, RenderBackend};
+
+/// Single operation performed on a `SoftwareRenderer`
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCall {
+    Clear(Color),
+    FillRect(Rect, Color),
+    DrawRect(Rect, Color),
+    Blit(String, Option<Rect>, Rect),
+    Text(String, String, ColorMod, i32, i32),
+}
+
+/// In-memory `RenderBackend`
+/// Rectangles are rasterized into a framebuffer, and every call is remembered in `calls`, so
+/// that tests can check what a screen draws without a window. Text is only recorded, glyphs
+/// take `glyph_size` of space per font, like the monospace atlases of `FontCache`.
+pub struct SoftwareRenderer {
+    size: (u32, u32),
+    pixels: Vec<Color>,
+    glyph_sizes: HashMap<String, (u32, u32)>,
+    /// Keys of the textures that can be blitted, their contents are not used
+    textures: HashSet<String>,
+    pub calls: Vec<DrawCall>,
+}
+
+impl SoftwareRenderer {
+    pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+        SoftwareRenderer {
+            size: (width, height),
+            pixels: vec![Color::RGB(0, 0, 0); (width * height) as usize],
+            glyph_sizes: HashMap::new(),
+            textures: HashSet::new(),
+            calls: Vec::new(),
+        }
+    }
+
+    /// Registers a font, each glyph of it will take `glyph_size` pixels
+    pub fn with_font<T: Into<String>>(mut self, key: T, glyph_size: (u32, u32)) -> SoftwareRenderer {
+        self.glyph_sizes.insert(key.into(), glyph_size);
+        self
+    }
+
+    /// Registers a texture, blitting it is only recorded
+    pub fn with_texture<T: Into<String>>(mut self, key: T) -> SoftwareRenderer {
+        self.textures.insert(key.into());
+        self
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x < self.size.0 && y < self.size.1 {
+            Some(self.pixels[(y * self.size.0 + x) as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the calls made so far and forgets them
+    pub fn take_calls(&mut self) -> Vec<DrawCall> {
+        let mut calls = Vec::new();
+        ::std::mem::swap(&mut calls, &mut self.calls);
+        calls
+    }
+
+    fn clip(&self, rect: Rect) -> Option<(u32, u32, u32, u32)> {
+        rect.intersection(Rect::new(0, 0, self.size.0, self.size.1))
+            .map(|r| (r.x() as u32, r.y() as u32, r.width(), r.height()))
+    }
+
+    fn fill(&mut self, rect: Rect, color: Color) {
+        if let Some((x, y, w, h)) = self.clip(rect) {
+            for row in y..y + h {
+                for column in x..x + w {
+                    self.pixels[(row * self.size.0 + column) as usize] = color;
+                }
+            }
+        }
+    }
+}
+
+impl RenderBackend for SoftwareRenderer {
+    fn output_size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    fn clear(&mut self, color: Color) {
+        for pixel in &mut self.pixels {
+            *pixel = color;
+        }
+        self.calls.push(DrawCall::Clear(color));
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), EngineError> {
+        self.fill(rect, color);
+        self.calls.push(DrawCall::FillRect(rect, color));
+        Ok(())
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<(), EngineError> {
+        let (x, y, w, h) = (rect.x(), rect.y(), rect.width(), rect.height());
+        self.fill(Rect::new(x, y, w, 1), color);
+        self.fill(Rect::new(x, y + h as i32 - 1, w, 1), color);
+        self.fill(Rect::new(x, y, 1, h), color);
+        self.fill(Rect::new(x + w as i32 - 1, y, 1, h), color);
+        self.calls.push(DrawCall::DrawRect(rect, color));
+        Ok(())
+    }
+
+    fn blit(&mut self, texture: &str, src: Option<Rect>, dst: Rect) -> Result<(), EngineError> {
+        if !self.textures.contains(texture) {
+            return Err(EngineError::MissingTexture(texture.to_string()));
+        }
+        self.calls.push(DrawCall::Blit(texture.to_string(), src, dst));
+        Ok(())
+    }
+
+    fn draw_text(&mut self,
+                 font: &str,
+                 text: &RenderableText,
+                 x: i32,
+                 y: i32)
+                 -> Result<(), EngineError> {
+        if !self.glyph_sizes.contains_key(font) {
+            return Err(EngineError::MissingFont(font.to_string()));
+        }
+        self.calls.push(DrawCall::Text(font.to_string(),
+                                       text.text().to_string(),
+                                       text.color_mod(),
+                                       x,
+                                       y));
+        Ok(())
+    }
+
+    fn text_size(&self, font: &str, text: &str) -> Result<(u32, u32), EngineError> {
+        let glyph = self.glyph_sizes
+            .get(font)
+            .ok_or_else(|| EngineError::MissingFont(font.to_string()))?;
+        Ok((text.chars().count() as u32 * glyph.0, glyph.1))
+    }
+
+    fn load_texture(&mut self, key: &str, _: &[u8]) -> Result<(), EngineError> {
+        self.textures.insert(key.to_string());
+        Ok(())
+    }
+
+    fn present(&mut self) {}
+
+    fn read_pixels(&mut self) -> Result<Frame, EngineError> {
+        let mut pixels = Vec::with_capacity(self.pixels.len() * 3);
+        for color in &self.pixels {
+            let (r, g, b, _) = color.rgba();
+            pixels.push(r);
+            pixels.push(g);
+            pixels.push(b);
+        }
+        Ok(Frame {
+            width: self.size.0,
+            height: self.size.1,
+            pixels: pixels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::pixels::Color::RGB;
+
+    use super::*;
+
+    #[test]
+    fn rects_are_rasterized() {
+        let mut r = SoftwareRenderer::new(8, 8);
+        r.fill_rect(Rect::new(6, 6, 4, 4), RGB(255, 0, 0)).unwrap();
+        r.draw_rect(Rect::new(0, 0, 4, 4), RGB(0, 255, 0)).unwrap();
+        assert_eq!(r.pixel(7, 7), Some(RGB(255, 0, 0)));
+        assert_eq!(r.pixel(5, 5), Some(RGB(0, 0, 0)));
+        assert_eq!(r.pixel(3, 0), Some(RGB(0, 255, 0)));
+        assert_eq!(r.pixel(1, 1), Some(RGB(0, 0, 0)));
+        assert_eq!(r.pixel(8, 0), None);
+        assert_eq!(r.take_calls(),
+                   vec![DrawCall::FillRect(Rect::new(6, 6, 4, 4), RGB(255, 0, 0)),
+                        DrawCall::DrawRect(Rect::new(0, 0, 4, 4), RGB(0, 255, 0))]);
+        assert!(r.calls.is_empty());
+    }
+
+    #[test]
+    fn text_needs_a_font() {
+        let mut r = SoftwareRenderer::new(100, 100).with_font("default", (8, 16));
+        assert_eq!(r.text_size("default", "four").unwrap(), (32, 16));
+        assert!(r.text_size("big", "four").is_err());
+        assert!(r.draw_text("big", &RenderableText::new("four"), 0, 0).is_err());
+        r.draw_text("default", &RenderableText::new("four"), 3, 4).unwrap();
+        assert_eq!(r.calls,
+                   vec![DrawCall::Text("default".to_string(),
+                                       "four".to_string(),
+                                       ColorMod::default(),
+                                       3,
+                                       4)]);
+    }
+}
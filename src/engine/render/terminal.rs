@@ -0,0 +1,338 @@
+use std::cmp;
+use std::io::{self, Write};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use engine::font::{ColorMod, RenderableText};
+use engine::error::EngineError;
+use engine::render::RenderBackend;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    ch: char,
+    fg: (u8, u8, u8),
+    bg: (u8, u8, u8),
+}
+
+impl Cell {
+    fn blank(bg: (u8, u8, u8)) -> Cell {
+        Cell {
+            ch: ' ',
+            fg: (255, 255, 255),
+            bg: bg,
+        }
+    }
+}
+
+/// `RenderBackend` drawing to a terminal with ANSI true color escape codes
+/// The logical drawing area is split into character cells: rectangles become colored cells
+/// (at least one, so that small entities stay visible), and text takes one cell per
+/// character regardless of the font, so menus turn into plain text lines.
+pub struct TerminalRenderer {
+    logical_size: (u32, u32),
+    grid: (u32, u32),
+    cells: Vec<Cell>,
+    /// Cells written to the terminal by the last `present`, unchanged frames are not resent
+    presented: Vec<Cell>,
+    out: Box<dyn Write>,
+}
+
+impl TerminalRenderer {
+    /// `grid` is the number of columns and rows of the terminal
+    pub fn new(logical_size: (u32, u32), grid: (u32, u32)) -> TerminalRenderer {
+        TerminalRenderer::with_output(logical_size, grid, Box::new(io::stdout()))
+    }
+
+    /// Same as `new`, but writes to `out` instead of stdout. A grid without cells gets one.
+    pub fn with_output(logical_size: (u32, u32),
+                       grid: (u32, u32),
+                       mut out: Box<dyn Write>)
+                       -> TerminalRenderer {
+        let grid = (cmp::max(grid.0, 1), cmp::max(grid.1, 1));
+        // clear the screen and hide the cursor
+        let _ = write!(out, "\x1b[2J\x1b[?25l");
+        let _ = out.flush();
+        TerminalRenderer {
+            logical_size: logical_size,
+            grid: grid,
+            cells: vec![Cell::blank((0, 0, 0)); (grid.0 * grid.1) as usize],
+            presented: Vec::new(),
+            out: out,
+        }
+    }
+
+    /// Size of a single cell in logical pixels
+    fn cell_size(&self) -> (u32, u32) {
+        (cmp::max(1, self.logical_size.0 / self.grid.0),
+         cmp::max(1, self.logical_size.1 / self.grid.1))
+    }
+
+    /// Range of cells covered by `rect`, as (first column, first row, last column, last row)
+    /// with the last ones exclusive
+    fn cell_range(&self, rect: Rect) -> Option<(u32, u32, u32, u32)> {
+        let clipped = match rect.intersection(Rect::new(0,
+                                                       0,
+                                                       self.logical_size.0,
+                                                       self.logical_size.1)) {
+            Some(r) => r,
+            None => return None,
+        };
+        let (cw, ch) = self.cell_size();
+        let x0 = cmp::min(clipped.x() as u32 / cw, self.grid.0 - 1);
+        let y0 = cmp::min(clipped.y() as u32 / ch, self.grid.1 - 1);
+        let x1 = cmp::min((clipped.x() as u32 + clipped.width() + cw - 1) / cw, self.grid.0);
+        let y1 = cmp::min((clipped.y() as u32 + clipped.height() + ch - 1) / ch, self.grid.1);
+        Some((x0, y0, cmp::max(x1, x0 + 1), cmp::max(y1, y0 + 1)))
+    }
+
+    fn cell_mut(&mut self, column: u32, row: u32) -> Option<&mut Cell> {
+        if column < self.grid.0 && row < self.grid.1 {
+            Some(&mut self.cells[(row * self.grid.0 + column) as usize])
+        } else {
+            None
+        }
+    }
+}
+
+fn rgb(color: Color) -> (u8, u8, u8) {
+    let (r, g, b, _) = color.rgba();
+    (r, g, b)
+}
+
+impl RenderBackend for TerminalRenderer {
+    fn output_size(&self) -> (u32, u32) {
+        self.logical_size
+    }
+
+    fn clear(&mut self, color: Color) {
+        let blank = Cell::blank(rgb(color));
+        for cell in &mut self.cells {
+            *cell = blank;
+        }
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), EngineError> {
+        if let Some((x0, y0, x1, y1)) = self.cell_range(rect) {
+            let blank = Cell::blank(rgb(color));
+            for row in y0..y1 {
+                for column in x0..x1 {
+                    if let Some(cell) = self.cell_mut(column, row) {
+                        *cell = blank;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<(), EngineError> {
+        if let Some((x0, y0, x1, y1)) = self.cell_range(rect) {
+            let fg = rgb(color);
+            for row in y0..y1 {
+                for column in x0..x1 {
+                    let horizontal = row == y0 || row == y1 - 1;
+                    let vertical = column == x0 || column == x1 - 1;
+                    let ch = match (horizontal, vertical) {
+                        (true, true) => '+',
+                        (true, false) => '-',
+                        (false, true) => '|',
+                        (false, false) => continue,
+                    };
+                    if let Some(cell) = self.cell_mut(column, row) {
+                        cell.ch = ch;
+                        cell.fg = fg;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn blit(&mut self, _: &str, _: Option<Rect>, dst: Rect) -> Result<(), EngineError> {
+        // there are no textures in a terminal, mark the area instead
+        if let Some((x0, y0, x1, y1)) = self.cell_range(dst) {
+            for row in y0..y1 {
+                for column in x0..x1 {
+                    if let Some(cell) = self.cell_mut(column, row) {
+                        cell.ch = '#';
+                        cell.fg = (200, 200, 200);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_text(&mut self,
+                 _: &str,
+                 text: &RenderableText,
+                 x: i32,
+                 y: i32)
+                 -> Result<(), EngineError> {
+        if x < 0 || y < 0 {
+            return Ok(());
+        }
+        let (cw, ch) = self.cell_size();
+        let ColorMod(r, g, b, _) = text.color_mod();
+        let row = y as u32 / ch;
+        let mut column = x as u32 / cw;
+        for character in text.text().chars() {
+            if let Some(cell) = self.cell_mut(column, row) {
+                cell.ch = character;
+                cell.fg = (r, g, b);
+            }
+            column += 1;
+        }
+        Ok(())
+    }
+
+    fn text_size(&self, _: &str, text: &str) -> Result<(u32, u32), EngineError> {
+        let (cw, ch) = self.cell_size();
+        Ok((text.chars().count() as u32 * cw, ch))
+    }
+
+    fn present(&mut self) {
+        if self.cells == self.presented {
+            return;
+        }
+        let mut frame = String::from("\x1b[H");
+        let mut current: Option<((u8, u8, u8), (u8, u8, u8))> = None;
+        for row in 0..self.grid.1 {
+            if row > 0 {
+                frame.push_str("\r\n");
+            }
+            for column in 0..self.grid.0 {
+                let cell = self.cells[(row * self.grid.0 + column) as usize];
+                if current != Some((cell.fg, cell.bg)) {
+                    frame.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                                            cell.fg.0,
+                                            cell.fg.1,
+                                            cell.fg.2,
+                                            cell.bg.0,
+                                            cell.bg.1,
+                                            cell.bg.2));
+                    current = Some((cell.fg, cell.bg));
+                }
+                frame.push(cell.ch);
+            }
+        }
+        frame.push_str("\x1b[0m");
+        let _ = self.out.write_all(frame.as_bytes());
+        let _ = self.out.flush();
+        self.presented = self.cells.clone();
+    }
+}
+
+impl Drop for TerminalRenderer {
+    fn drop(&mut self) {
+        // reset colors, show the cursor and clear the screen
+        let _ = write!(self.out, "\x1b[0m\x1b[?25h\x1b[2J\x1b[H");
+        let _ = self.out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    use sdl2::pixels::Color::RGB;
+    use sdl2::rect::Rect;
+
+    use engine::font::RenderableText;
+    use engine::render::RenderBackend;
+    use super::TerminalRenderer;
+
+    /// Keeps what the renderer writes, shared with the test
+    #[derive(Clone)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Output {
+        fn take(&self) -> String {
+            let bytes = self.0.borrow_mut().drain(..).collect();
+            String::from_utf8(bytes).unwrap()
+        }
+    }
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// 8x4 cells of 10x10 logical pixels
+    fn renderer() -> (TerminalRenderer, Output) {
+        let output = Output(Rc::new(RefCell::new(Vec::new())));
+        let r = TerminalRenderer::with_output((80, 40), (8, 4), Box::new(output.clone()));
+        output.take();
+        (r, output)
+    }
+
+    fn row_text(r: &TerminalRenderer, row: u32) -> String {
+        (0..r.grid.0).map(|column| r.cells[(row * r.grid.0 + column) as usize].ch).collect()
+    }
+
+    #[test]
+    fn rects_cover_the_cells_they_touch() {
+        let (r, _) = renderer();
+        assert_eq!(r.cell_range(Rect::new(15, 5, 20, 10)), Some((1, 0, 4, 2)));
+        // clipped to the drawing area
+        assert_eq!(r.cell_range(Rect::new(-20, -20, 30, 30)), Some((0, 0, 1, 1)));
+        assert_eq!(r.cell_range(Rect::new(75, 35, 20, 20)), Some((7, 3, 8, 4)));
+        assert_eq!(r.cell_range(Rect::new(100, 0, 10, 10)), None);
+    }
+
+    #[test]
+    fn small_rects_take_a_whole_cell() {
+        let (mut r, _) = renderer();
+        assert_eq!(r.cell_range(Rect::new(12, 12, 1, 1)), Some((1, 1, 2, 2)));
+        r.fill_rect(Rect::new(12, 12, 1, 1), RGB(255, 0, 0)).unwrap();
+        assert_eq!(r.cells[(r.grid.0 + 1) as usize].bg, (255, 0, 0));
+        assert_eq!(r.cells.iter().filter(|cell| cell.bg == (255, 0, 0)).count(), 1);
+    }
+
+    #[test]
+    fn text_takes_a_cell_per_character_and_is_cut_at_the_edge() {
+        let (mut r, _) = renderer();
+        r.draw_text("default", &RenderableText::new("Menu"), 0, 0).unwrap();
+        r.draw_text("default", &RenderableText::new("Hello"), 50, 10).unwrap();
+        r.draw_text("default", &RenderableText::new("Off"), -10, 20).unwrap();
+        assert_eq!(row_text(&r, 0), "Menu    ");
+        assert_eq!(row_text(&r, 1), "     Hel");
+        assert_eq!(row_text(&r, 2), "        ");
+    }
+
+    #[test]
+    fn unchanged_frames_are_not_sent_again() {
+        let (mut r, output) = renderer();
+        r.fill_rect(Rect::new(0, 0, 10, 10), RGB(0, 0, 255)).unwrap();
+        r.present();
+        let frame = output.take();
+        assert!(frame.starts_with("\x1b[H"));
+        assert!(frame.contains("\x1b[48;2;0;0;255m"));
+
+        r.present();
+        assert_eq!(output.take(), "");
+
+        r.fill_rect(Rect::new(0, 0, 10, 10), RGB(0, 255, 0)).unwrap();
+        r.present();
+        assert!(output.take().contains("\x1b[48;2;0;255;0m"));
+    }
+
+    #[test]
+    fn a_grid_without_cells_gets_one() {
+        let output = Output(Rc::new(RefCell::new(Vec::new())));
+        let mut r = TerminalRenderer::with_output((80, 40), (0, 0), Box::new(output));
+        r.fill_rect(Rect::new(5, 5, 10, 10), RGB(255, 0, 0)).unwrap();
+        r.draw_text("default", &RenderableText::new("Hi"), 0, 0).unwrap();
+        assert_eq!(r.grid, (1, 1));
+        assert_eq!(row_text(&r, 0), "H");
+    }
+}
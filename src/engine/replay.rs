@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use sdl2::keyboard::Keycode;
+
+use engine::data::SettingsT;
+use engine::input::Input;
+use engine::message::{EngineEvent, MessageT};
+
+const HEADER: &'static str = "hungry-pixel-replay 2";
+
+/// Replay file writer
+/// The file starts with a header holding the seed and every field of the game settings, then
+/// one line per recorded message: `T <ms>` for ticks, `AP <action>` and `AR <action>` for
+/// the actions the player's buttons were bound to, `P <key>` and `R <key>` for unbound
+/// buttons, `CP <input>` and `CR <input>` for unbound game controller ones (see
+/// `Input::config_name`). Recording the actions rather than the buttons makes the replay
+/// play the same whatever the bindings are when it is watched.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>, S: SettingsT>(path: P,
+                                                seed: u32,
+                                                settings: &S)
+                                                -> io::Result<Recorder> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "seed {}", seed)?;
+        for field in S::fields() {
+            writeln!(writer, "setting {} {}", field, settings.get(field).unwrap())?;
+        }
+        writeln!(writer, "events")?;
+        Ok(Recorder { writer: writer })
+    }
+
+    /// Writes the message down if it is one of the replayable ones, others are ignored
+    pub fn record<M: MessageT>(&mut self, msg: &M) -> io::Result<()> {
+        match msg.event() {
+            Some(EngineEvent::Tick(x)) => writeln!(self.writer, "T {}", x),
+            Some(EngineEvent::ButtonPressed(k)) => writeln!(self.writer, "P {}", k.name()),
+            Some(EngineEvent::ButtonReleased(k)) => writeln!(self.writer, "R {}", k.name()),
+            Some(EngineEvent::ControllerPressed(input)) => {
+                writeln!(self.writer, "CP {}", input.config_name())
+            }
+            Some(EngineEvent::ControllerReleased(input)) => {
+                writeln!(self.writer, "CR {}", input.config_name())
+            }
+            Some(EngineEvent::ActionPressed(action)) => {
+                writeln!(self.writer, "AP {}", action.to_string().to_lowercase())
+            }
+            Some(EngineEvent::ActionReleased(action)) => {
+                writeln!(self.writer, "AR {}", action.to_string().to_lowercase())
+            }
+            Some(EngineEvent::FocusLost) => writeln!(self.writer, "F lost"),
+            Some(EngineEvent::FocusGained) => writeln!(self.writer, "F gained"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Replay loaded from a file written by `Recorder`, with the game's messages `M` and
+/// settings `S`
+pub struct Replay<M, S> {
+    pub seed: u32,
+    pub settings: S,
+    events: VecDeque<M>,
+}
+
+impl<M: MessageT, S: SettingsT> Replay<M, S> {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay<M, S>, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut lines = BufReader::new(file).lines();
+        let mut next_line = || -> Result<Option<String>, String> {
+            match lines.next() {
+                Some(line) => line.map(Some).map_err(|e| e.to_string()),
+                None => Ok(None),
+            }
+        };
+
+        if next_line()?.as_ref().map(|l| l.as_str()) != Some(HEADER) {
+            return Err("Not a replay file".to_string());
+        }
+
+        let mut seed = None;
+        let mut settings = S::new();
+        loop {
+            let line = next_line()?.ok_or_else(|| "Unexpected end of replay header".to_string())?;
+            if line == "events" {
+                break;
+            }
+            let mut parts = line.splitn(3, ' ');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("seed"), Some(value), None) => {
+                    seed = Some(value.parse::<u32>()
+                        .map_err(|_| format!("Invalid seed: {}", value))?)
+                }
+                (Some("setting"), Some(key), Some(value)) => settings.set(key, value)?,
+                _ => return Err(format!("Invalid replay header line: {}", line)),
+            }
+        }
+
+        let mut events = VecDeque::new();
+        while let Some(line) = next_line()? {
+            if line.is_empty() {
+                continue;
+            }
+            events.push_back(Self::parse_event(&line)?);
+        }
+
+        Ok(Replay {
+            seed: seed.ok_or_else(|| "Replay has no seed".to_string())?,
+            settings: settings,
+            events: events,
+        })
+    }
+
+    fn parse_event(line: &str) -> Result<M, String> {
+        let mut parts = line.splitn(2, ' ');
+        let kind = parts.next().unwrap_or("");
+        let value = parts.next().unwrap_or("");
+        let key = || Keycode::from_name(value).ok_or_else(|| format!("Unknown key: {}", value));
+        let event = match kind {
+            "T" => {
+                value.parse::<u32>()
+                    .map(EngineEvent::Tick)
+                    .map_err(|_| format!("Invalid tick: {}", line))
+            }
+            "P" => key().map(EngineEvent::ButtonPressed),
+            "R" => key().map(EngineEvent::ButtonReleased),
+            "AP" => value.parse::<M::Action>().map(EngineEvent::ActionPressed),
+            "AR" => value.parse::<M::Action>().map(EngineEvent::ActionReleased),
+            "CP" => value.parse::<Input>().map(EngineEvent::ControllerPressed),
+            "CR" => value.parse::<Input>().map(EngineEvent::ControllerReleased),
+            "F" if value == "lost" => Ok(EngineEvent::FocusLost),
+            "F" if value == "gained" => Ok(EngineEvent::FocusGained),
+            _ => Err(format!("Invalid replay event: {}", line)),
+        };
+        event.map(M::from_event)
+    }
+
+    /// Returns the messages of the next recorded simulation step: button events followed by
+    /// the tick they were processed with, in the order they were recorded
+    pub fn next_step(&mut self) -> Option<Vec<M>> {
+        if self.events.is_empty() {
+            return None;
+        }
+        let mut step = Vec::new();
+        while let Some(msg) = self.events.pop_front() {
+            step.push(msg);
+            if let Some(EngineEvent::Tick(_)) = msg.event() {
+                break;
+            }
+        }
+        Some(step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use sdl2::controller::{Axis, Button};
+    use sdl2::keyboard::Keycode;
+
+    use engine::data::SettingsT;
+    use engine::input::Input;
+    use engine::message::EngineEvent::*;
+    use engine::message::testing::{TestAction, TestMsg, TestSettings};
+    use testing::{temp_path, with_text_file};
+    use super::{Recorder, Replay, HEADER};
+
+    fn load_text(name: &str, text: &str) -> Result<Replay<TestMsg, TestSettings>, String> {
+        with_text_file(&format!("replay-{}", name), text, |path| Replay::load(path))
+    }
+
+    #[test]
+    fn recorded_messages_are_replayed() {
+        let path = temp_path("replay-round-trip");
+        let mut settings = TestSettings::new();
+        settings.set("speed", "55").unwrap();
+        let messages = [TestMsg::Event(ButtonPressed(Keycode::Q)),
+                        TestMsg::Event(ActionPressed(TestAction::Duck)),
+                        TestMsg::Event(Tick(16)),
+                        TestMsg::Event(ActionReleased(TestAction::Duck)),
+                        TestMsg::Event(ControllerPressed(Input::Button(Button::X))),
+                        TestMsg::Event(FocusLost),
+                        TestMsg::Event(Tick(16)),
+                        TestMsg::Event(ControllerReleased(Input::Axis(Axis::RightX, false))),
+                        TestMsg::Event(ButtonReleased(Keycode::Q)),
+                        TestMsg::Event(FocusGained),
+                        TestMsg::Event(Exit),
+                        TestMsg::Event(Tick(16))];
+        {
+            let mut recorder = Recorder::create(&path, 1234, &settings).unwrap();
+            for msg in messages.iter() {
+                recorder.record(msg).unwrap();
+            }
+        }
+        let mut replay: Replay<TestMsg, TestSettings> = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(replay.seed, 1234);
+        assert_eq!(replay.settings.get("speed"), Some("55".to_string()));
+        let mut steps = Vec::new();
+        while let Some(step) = replay.next_step() {
+            steps.push(format!("{:?}", step));
+        }
+        // everything but the exit, which is not replayable
+        let last = [messages[7], messages[8], messages[9], messages[11]];
+        let expected: [&[TestMsg]; 3] = [&messages[0..3], &messages[3..7], &last];
+        let expected: Vec<String> = expected.iter().map(|step| format!("{:?}", step)).collect();
+        assert_eq!(steps, expected);
+    }
+
+    #[test]
+    fn bad_headers_are_rejected() {
+        assert!(load_text("not-a-replay", "hungry-pixel-replay 0\nseed 1\nevents\n").is_err());
+        let header = format!("{}\nseed 1\nsetting speed fast\nevents\n", HEADER);
+        assert!(load_text("bad-setting", &header).is_err());
+        let header = format!("{}\nsetting speed 40\nevents\n", HEADER);
+        assert!(load_text("no-seed", &header).is_err());
+        let header = format!("{}\nseed 1\n", HEADER);
+        assert!(load_text("no-events", &header).is_err());
+    }
+
+    #[test]
+    fn bad_events_are_rejected() {
+        let header = format!("{}\nseed 1\nevents\n", HEADER);
+        assert!(load_text("good-events", &format!("{}T 16\nP W\n", header)).is_ok());
+        for line in ["T soon", "P Nope", "AP fly", "CP button nope", "F away", "X 1"].iter() {
+            let text = format!("{}T 16\n{}\n", header, line);
+            assert!(load_text("bad-event", &text).is_err(), "{} was accepted", line);
+        }
+    }
+}
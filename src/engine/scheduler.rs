@@ -0,0 +1,221 @@
+use std::fmt::{Debug, Formatter, Error};
+
+/// Identifies a scheduled message, so that it can be cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerHandle(u32);
+
+/// How long to wait before a scheduled message is sent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delay {
+    /// Milliseconds of simulation time, as carried by ticks
+    Millis(u32),
+    /// Number of ticks
+    Ticks(u32),
+}
+
+struct Scheduled<M> {
+    handle: TimerHandle,
+    msg: M,
+    delay: Delay,
+    /// Milliseconds or ticks left, depending on `delay`
+    remaining: u32,
+    repeat: bool,
+}
+
+/// Message scheduler
+/// Holds messages that should be sent later (once or repeatedly); the engine advances it on
+/// every tick and puts due messages into its queue. Since it is driven by ticks it is as
+/// deterministic as the rest of the simulation.
+pub struct Scheduler<M> {
+    next_handle: u32,
+    scheduled: Vec<Scheduled<M>>,
+}
+
+impl<M: Clone> Scheduler<M> {
+    pub fn new() -> Scheduler<M> {
+        Scheduler {
+            next_handle: 0,
+            scheduled: Vec::new(),
+        }
+    }
+
+    /// Sends `msg` once, after `delay`
+    pub fn after(&mut self, delay: Delay, msg: M) -> TimerHandle {
+        self.schedule(delay, msg, false)
+    }
+
+    /// Sends `msg` every `delay` until cancelled
+    pub fn every(&mut self, delay: Delay, msg: M) -> TimerHandle {
+        self.schedule(delay, msg, true)
+    }
+
+    /// Same as `after`, but the message is built from the handle of the timer, so that the
+    /// receiver can tell its own timers apart, e.g. `after_with(delay, Msg::TimerFired)`
+    pub fn after_with(&mut self, delay: Delay, f: fn(TimerHandle) -> M) -> TimerHandle {
+        let handle = TimerHandle(self.next_handle);
+        self.schedule(delay, f(handle), false)
+    }
+
+    /// Removes a scheduled message; returns whether it was still scheduled
+    pub fn cancel(&mut self, handle: TimerHandle) -> bool {
+        let before = self.scheduled.len();
+        self.scheduled.retain(|s| s.handle != handle);
+        self.scheduled.len() != before
+    }
+
+    pub fn is_scheduled(&self, handle: TimerHandle) -> bool {
+        self.scheduled.iter().any(|s| s.handle == handle)
+    }
+
+    /// Number of messages waiting to be sent
+    pub fn len(&self) -> usize {
+        self.scheduled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scheduled.is_empty()
+    }
+
+    /// Advances all the timers by a tick of `dt` milliseconds and returns the messages that
+    /// became due, in the order they were scheduled. A repeating message is sent as many
+    /// times as its delay fits into the tick.
+    pub fn advance(&mut self, dt: u32) -> Vec<M> {
+        let mut due = Vec::new();
+        for scheduled in &mut self.scheduled {
+            let mut passed = match scheduled.delay {
+                Delay::Millis(_) => dt,
+                Delay::Ticks(_) => 1,
+            };
+            while passed >= scheduled.remaining {
+                passed -= scheduled.remaining;
+                scheduled.remaining = 0;
+                due.push(scheduled.msg.clone());
+                if !scheduled.repeat {
+                    break;
+                }
+                scheduled.remaining = Self::initial(scheduled.delay);
+                // with no delay it would be sent forever, so it is sent once a tick
+                if scheduled.remaining == 0 {
+                    break;
+                }
+            }
+            scheduled.remaining = scheduled.remaining.saturating_sub(passed);
+        }
+        self.scheduled.retain(|s| s.repeat || s.remaining > 0);
+        due
+    }
+
+    /// Forgets everything scheduled
+    pub fn clear(&mut self) {
+        self.scheduled.clear();
+    }
+
+    fn schedule(&mut self, delay: Delay, msg: M, repeat: bool) -> TimerHandle {
+        let handle = TimerHandle(self.next_handle);
+        self.next_handle = self.next_handle.wrapping_add(1);
+        self.scheduled.push(Scheduled {
+            handle: handle,
+            msg: msg,
+            delay: delay,
+            remaining: Self::initial(delay),
+            repeat: repeat,
+        });
+        handle
+    }
+
+    fn initial(delay: Delay) -> u32 {
+        match delay {
+            Delay::Millis(x) | Delay::Ticks(x) => x,
+        }
+    }
+}
+
+impl<M> Debug for Scheduler<M> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Scheduler {{ {} messages }}", self.scheduled.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Delay, Scheduler, TimerHandle};
+
+    #[test]
+    fn messages_are_sent_once_after_their_delay() {
+        let mut scheduler = Scheduler::new();
+        scheduler.after(Delay::Millis(50), "late");
+        scheduler.after(Delay::Millis(20), "early");
+        assert_eq!(scheduler.advance(16), Vec::<&str>::new());
+        assert_eq!(scheduler.advance(16), vec!["early"]);
+        assert_eq!(scheduler.advance(16), Vec::<&str>::new());
+        assert_eq!(scheduler.advance(16), vec!["late"]);
+        assert!(scheduler.is_empty());
+        assert_eq!(scheduler.advance(1000), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn tick_delays_ignore_the_tick_length() {
+        let mut scheduler = Scheduler::new();
+        scheduler.after(Delay::Ticks(2), "ticks");
+        scheduler.after(Delay::Millis(2), "millis");
+        assert_eq!(scheduler.advance(100), vec!["millis"]);
+        assert_eq!(scheduler.advance(100), vec!["ticks"]);
+    }
+
+    #[test]
+    fn repeating_messages_are_sent_until_cancelled() {
+        let mut scheduler = Scheduler::new();
+        let handle = scheduler.every(Delay::Ticks(2), "again");
+        let sent: Vec<Vec<&str>> = (0..4).map(|_| scheduler.advance(16)).collect();
+        assert_eq!(sent, vec![vec![], vec!["again"], vec![], vec!["again"]]);
+        assert!(scheduler.cancel(handle));
+        assert!(!scheduler.is_scheduled(handle));
+        assert!(!scheduler.cancel(handle));
+        assert_eq!(scheduler.advance(16), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn repeating_messages_are_sent_for_every_delay_in_a_long_tick() {
+        let mut scheduler = Scheduler::new();
+        scheduler.every(Delay::Millis(10), "again");
+        assert_eq!(scheduler.advance(35), vec!["again", "again", "again"]);
+        // what was left of the tick counts towards the next one
+        assert_eq!(scheduler.advance(5), vec!["again"]);
+    }
+
+    #[test]
+    fn repeating_messages_without_delay_are_sent_once_a_tick() {
+        let mut scheduler = Scheduler::new();
+        scheduler.every(Delay::Millis(0), "now");
+        assert_eq!(scheduler.advance(16), vec!["now"]);
+        assert_eq!(scheduler.advance(16), vec!["now"]);
+    }
+
+    #[test]
+    fn timers_can_be_cancelled_by_their_receiver() {
+        let mut scheduler = Scheduler::new();
+        let handle = scheduler.every(Delay::Ticks(1), "again");
+        let mut received = 0;
+        for _ in 0..3 {
+            for _ in scheduler.advance(16) {
+                // as a state would on getting the message
+                received += 1;
+                scheduler.cancel(handle);
+            }
+        }
+        assert_eq!(received, 1);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn fired_timers_carry_their_handle() {
+        fn own(handle: TimerHandle) -> TimerHandle {
+            handle
+        }
+        let mut scheduler = Scheduler::new();
+        let handle = scheduler.after_with(Delay::Ticks(1), own);
+        assert_eq!(scheduler.advance(16), vec![handle]);
+        // sent messages are gone, there is nothing left to cancel
+        assert!(!scheduler.cancel(handle));
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use engine::error::{EngineError, OrLog};
+use engine::render::RenderBackend;
+
+/// Part of a sprite sheet texture
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    /// Key the sheet texture is loaded under, its file name
+    pub sheet: String,
+    /// Where the sprite is on the sheet
+    pub src: Rect,
+}
+
+/// Sprites by name
+/// Sprites are described in a text file with one `name sheet x y width height` line per
+/// sprite, e.g. `player sprites.png 0 0 16 16`; `#` starts a comment. Entities draw
+/// themselves with `draw`, which falls back to a solid color when their sprite is missing.
+#[derive(Debug)]
+pub struct Sprites {
+    sprites: HashMap<String, Sprite>,
+}
+
+impl Sprites {
+    pub fn new() -> Sprites {
+        Sprites { sprites: HashMap::new() }
+    }
+
+    /// Parses a sprites description, returns the sprites by name
+    pub fn parse(text: &str) -> Result<Vec<(String, Sprite)>, String> {
+        let mut sprites = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() != 6 {
+                return Err(format!("line {}: expected name, sheet, x, y, width and height",
+                                   index + 1));
+            }
+            // the corners of the sprite have to be valid coordinates
+            let max = i32::max_value() as u32;
+            let mut numbers = Vec::new();
+            for part in &parts[2..] {
+                let number = part.parse::<u32>()
+                    .map_err(|_| format!("line {}: invalid number {}", index + 1, part))?;
+                if number > max {
+                    return Err(format!("line {}: {} is out of range", index + 1, part));
+                }
+                numbers.push(number);
+            }
+            if numbers[0] + numbers[2] > max || numbers[1] + numbers[3] > max {
+                return Err(format!("line {}: the sprite is out of range", index + 1));
+            }
+            if numbers[2] == 0 || numbers[3] == 0 {
+                return Err(format!("line {}: the sprite is empty", index + 1));
+            }
+            let sprite = Sprite {
+                sheet: parts[1].to_string(),
+                src: Rect::new(numbers[0] as i32, numbers[1] as i32, numbers[2], numbers[3]),
+            };
+            sprites.push((parts[0].to_string(), sprite));
+        }
+        Ok(sprites)
+    }
+
+    pub fn insert<T: Into<String>>(&mut self, name: T, sprite: Sprite) {
+        self.sprites.insert(name.into(), sprite);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Sprite> {
+        self.sprites.get(name)
+    }
+
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Draws the sprite called `name` scaled to `dst`; fills `dst` with `fallback` instead if
+    /// there is no such sprite or it can not be drawn
+    pub fn draw(&self,
+                r: &mut RenderBackend,
+                name: &str,
+                dst: Rect,
+                fallback: Color)
+                -> Result<(), EngineError> {
+        if let Some(sprite) = self.sprites.get(name) {
+            if r.blit(&sprite.sheet, Some(sprite.src), dst).or_log().is_some() {
+                return Ok(());
+            }
+        }
+        r.fill_rect(dst, fallback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::pixels::Color::RGB;
+    use sdl2::rect::Rect;
+
+    use engine::render::software::{DrawCall, SoftwareRenderer};
+    use super::{Sprite, Sprites};
+
+    fn sprite(sheet: &str, x: i32, y: i32, width: u32, height: u32) -> Sprite {
+        Sprite {
+            sheet: sheet.to_string(),
+            src: Rect::new(x, y, width, height),
+        }
+    }
+
+    #[test]
+    fn sprites_are_read_by_line() {
+        let text = "# name sheet x y width height\n\
+                    player sprites.png 0 0 16 16\n\
+                    \n\
+                    spike  spikes.png 16 8 8 8  # pointy\n";
+        assert_eq!(Sprites::parse(text),
+                   Ok(vec![("player".to_string(), sprite("sprites.png", 0, 0, 16, 16)),
+                           ("spike".to_string(), sprite("spikes.png", 16, 8, 8, 8))]));
+    }
+
+    #[test]
+    fn broken_lines_are_refused() {
+        assert_eq!(Sprites::parse("player sprites.png 0 0 16"),
+                   Err("line 1: expected name, sheet, x, y, width and height".to_string()));
+        assert_eq!(Sprites::parse("\nplayer sprites.png 0 -1 16 16"),
+                   Err("line 2: invalid number -1".to_string()));
+        assert_eq!(Sprites::parse("player sprites.png 0 0 0 16"),
+                   Err("line 1: the sprite is empty".to_string()));
+    }
+
+    #[test]
+    fn sprites_out_of_range_are_refused() {
+        assert_eq!(Sprites::parse("player sprites.png 4294967295 0 16 16"),
+                   Err("line 1: 4294967295 is out of range".to_string()));
+        assert_eq!(Sprites::parse("player sprites.png 0 2147483647 16 16"),
+                   Err("line 1: the sprite is out of range".to_string()));
+    }
+
+    #[test]
+    fn sprites_are_blitted_from_their_sheet() {
+        let mut r = SoftwareRenderer::new(64, 64).with_texture("sprites.png");
+        let mut sprites = Sprites::new();
+        sprites.insert("player", sprite("sprites.png", 16, 0, 16, 16));
+        let dst = Rect::new(10, 10, 32, 32);
+        sprites.draw(&mut r, "player", dst, RGB(255, 0, 0)).unwrap();
+        assert_eq!(r.take_calls(),
+                   vec![DrawCall::Blit("sprites.png".to_string(),
+                                       Some(Rect::new(16, 0, 16, 16)),
+                                       dst)]);
+    }
+
+    #[test]
+    fn missing_sprites_and_sheets_fall_back_to_a_color() {
+        let mut r = SoftwareRenderer::new(64, 64).with_texture("sprites.png");
+        let mut sprites = Sprites::new();
+        sprites.insert("spike", sprite("spikes.png", 0, 0, 8, 8));
+        let dst = Rect::new(10, 10, 8, 8);
+        sprites.draw(&mut r, "spike", dst, RGB(255, 0, 0)).unwrap();
+        sprites.draw(&mut r, "edible", dst, RGB(0, 255, 0)).unwrap();
+        assert_eq!(r.take_calls(),
+                   vec![DrawCall::FillRect(dst, RGB(255, 0, 0)),
+                        DrawCall::FillRect(dst, RGB(0, 255, 0))]);
+        assert_eq!(r.pixel(10, 10), Some(RGB(0, 255, 0)));
+    }
+}
@@ -0,0 +1,323 @@
+use std::cmp;
+
+use engine::registry::StateRegistry;
+use engine::render::RenderBackend;
+use engine::state::StateT;
+use engine::debug::StateInfo;
+use log::Category;
+
+/// States stack manipulation, states are referred to by the names they are registered
+/// under in the `StateRegistry`
+#[derive(Debug, Clone, Copy)]
+pub enum StackCommand {
+    /// Pushes a new instance of the state on top of the stack
+    Push(&'static str),
+    /// Replaces the topmost state with a new instance of the state
+    Replace(&'static str),
+    /// Empties the stack and pushes a new instance of the state
+    ClearAndPush(&'static str),
+    /// Pops states until the named one is on top
+    PopTo(&'static str),
+    /// Pops the given number of states
+    Pop(usize),
+}
+
+/// A state on the stack, along with the name it was created by
+pub struct StackEntry<M, D> {
+    pub name: String,
+    pub state: Box<StateT<Message = M, EngineData = D>>,
+}
+
+/// Stack of states
+/// Does not know anything about the game: it is generic over the message type `M` and the
+/// data `D` shared by all the states. It propagates messages from the top down, renders
+/// from the topmost fullscreen state up, and applies `StackCommand`s with the states
+/// created by its `registry`, calling the lifecycle hooks of `StateT` on the way.
+pub struct StateStack<M, D> {
+    entries: Vec<StackEntry<M, D>>,
+    /// Factories for all the states that can be pushed, by name
+    pub registry: StateRegistry<M, D>,
+}
+
+impl<M, D> StateStack<M, D> {
+    pub fn new() -> StateStack<M, D> {
+        StateStack {
+            entries: Vec::new(),
+            registry: StateRegistry::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Name of the topmost state
+    pub fn top(&self) -> Option<&str> {
+        self.entries.last().map(|entry| entry.name.as_str())
+    }
+
+    /// Creates the state registered under `name` and puts it on top of the stack
+    pub fn push(&mut self, name: &str, data: &mut D) -> Result<(), String> {
+        self.push_named(name, data, true)
+    }
+
+    /// Applies a stack command. Unknown state names, and names that are not on the stack for
+    /// `PopTo`, are reported as errors and leave the stack as it was. The bottom state is
+    /// never popped, so there is always something to send messages to.
+    pub fn apply(&mut self, command: StackCommand, data: &mut D) -> Result<(), String> {
+        match command {
+            StackCommand::Push(name) => self.push(name, data),
+            StackCommand::Replace(name) => {
+                self.check_registered(name)?;
+                self.exit_top(data);
+                self.push_named(name, data, false)
+            }
+            StackCommand::ClearAndPush(name) => {
+                self.check_registered(name)?;
+                while self.exit_top(data) {}
+                self.push_named(name, data, false)
+            }
+            StackCommand::PopTo(name) => {
+                let index = match self.entries.iter().rposition(|entry| entry.name == name) {
+                    Some(index) => index,
+                    None => return Err(format!("{} is not on the stack", name)),
+                };
+                let count = self.entries.len() - 1 - index;
+                self.pop(count, data);
+                Ok(())
+            }
+            StackCommand::Pop(x) => {
+                let count = cmp::min(x, self.entries.len().saturating_sub(1));
+                if count < x {
+                    warn!(Category::State, "Not popping the last state");
+                }
+                self.pop(count, data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Passes `msg` down the stack, starting from the topmost state, until a state
+    /// consumes it. Returns whatever fell through the whole stack.
+    pub fn propagate(&mut self, data: &mut D, msg: M) -> Option<M> {
+        let mut current_msg = msg;
+        for index in (0..self.entries.len()).rev() {
+            match self.entries[index].state.process_message(data, current_msg) {
+                None => return None,
+                Some(message) => current_msg = message,
+            }
+        }
+        Some(current_msg)
+    }
+
+    /// Offers a console command to the states from the top down, returns the output of the
+    /// first one that knows it
+    pub fn console_command(&mut self, data: &mut D, args: &[&str]) -> Option<String> {
+        for index in (0..self.entries.len()).rev() {
+            if let Some(output) = self.entries[index].state.console_command(data, args) {
+                return Some(output);
+            }
+        }
+        None
+    }
+
+    /// Tells every state, bottom up, that assets or settings were reloaded
+    pub fn reload(&mut self, data: &mut D) {
+        for entry in &mut self.entries {
+            entry.state.on_reload(data);
+        }
+    }
+
+    /// Renders the topmost fullscreen state and everything above it, bottom up
+    pub fn render(&mut self, r: &mut RenderBackend, data: &mut D) {
+        for index in self.first_visible()..self.entries.len() {
+            self.entries[index].state.render(r, data);
+        }
+    }
+
+    /// Same as `render`, but draws the debugging aids of the states
+    pub fn render_debug(&mut self, r: &mut RenderBackend, data: &mut D) {
+        for index in self.first_visible()..self.entries.len() {
+            self.entries[index].state.render_debug(r, data);
+        }
+    }
+
+    /// Describes the states, from the bottom of the stack up
+    pub fn describe(&self) -> Vec<StateInfo> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                StateInfo {
+                    name: entry.name.clone(),
+                    type_name: entry.state.debug_name(),
+                    fullscreen: entry.state.is_fullscreen(),
+                    details: entry.state.debug_details(),
+                }
+            })
+            .collect()
+    }
+
+    /// Index of the topmost fullscreen state, nothing below it is visible
+    fn first_visible(&self) -> usize {
+        for index in (0..self.entries.len()).rev() {
+            if self.entries[index].state.is_fullscreen() {
+                return index;
+            }
+        }
+        0
+    }
+
+    /// Pushes a new state, `cover` tells whether the state below has to be told it is covered
+    fn push_named(&mut self, name: &str, data: &mut D, cover: bool) -> Result<(), String> {
+        let state = match self.registry.create(name, data) {
+            Some(state) => state,
+            None => return Err(format!("No state registered as {}", name)),
+        };
+        if cover {
+            if let Some(top) = self.entries.last_mut() {
+                debug!(Category::State, "Covering {}", top.name);
+                top.state.on_cover(data);
+            }
+        }
+        debug!(Category::State, "Pushing {}", name);
+        self.entries.push(StackEntry {
+            name: name.to_string(),
+            state: state,
+        });
+        if let Some(top) = self.entries.last_mut() {
+            top.state.on_enter(data);
+        }
+        Ok(())
+    }
+
+    /// Removes the topmost state, returns whether there was one
+    fn exit_top(&mut self, data: &mut D) -> bool {
+        match self.entries.pop() {
+            Some(mut entry) => {
+                debug!(Category::State, "Popping {}", entry.name);
+                entry.state.on_exit(data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn check_registered(&self, name: &str) -> Result<(), String> {
+        if self.registry.contains(name) {
+            Ok(())
+        } else {
+            Err(format!("No state registered as {}", name))
+        }
+    }
+
+    /// Removes `count` states and tells the new topmost one it is uncovered
+    fn pop(&mut self, count: usize, data: &mut D) {
+        let mut popped = false;
+        for _ in 0..count {
+            popped |= self.exit_top(data);
+        }
+        if popped {
+            self.uncover_top(data);
+        }
+    }
+
+    fn uncover_top(&mut self, data: &mut D) {
+        if let Some(top) = self.entries.last_mut() {
+            debug!(Category::State, "Uncovering {}", top.name);
+            top.state.on_uncover(data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::render::RenderBackend;
+    use engine::state::StateT;
+    use super::{StackCommand, StateStack};
+
+    /// Writes its lifecycle down in the shared data
+    struct Named(&'static str);
+
+    impl StateT for Named {
+        type Message = u32;
+        type EngineData = Vec<String>;
+
+        fn process_message(&mut self, _: &mut Vec<String>, msg: u32) -> Option<u32> {
+            Some(msg)
+        }
+
+        fn render(&mut self, _: &mut RenderBackend, _: &mut Vec<String>) {}
+
+        fn is_fullscreen(&self) -> bool {
+            true
+        }
+
+        fn on_exit(&mut self, log: &mut Vec<String>) {
+            log.push(format!("exit {}", self.0));
+        }
+
+        fn on_uncover(&mut self, log: &mut Vec<String>) {
+            log.push(format!("uncover {}", self.0));
+        }
+    }
+
+    fn stack(names: &[&'static str]) -> (StateStack<u32, Vec<String>>, Vec<String>) {
+        let mut stack = StateStack::new();
+        for &name in &["a", "b", "c"] {
+            stack.registry.register(name, move |_: &mut Vec<String>| {
+                Box::new(Named(name)) as Box<StateT<Message = u32, EngineData = Vec<String>>>
+            });
+        }
+        let mut log = Vec::new();
+        for name in names {
+            stack.push(name, &mut log).unwrap();
+        }
+        (stack, log)
+    }
+
+    #[test]
+    fn unknown_states_leave_the_stack_alone() {
+        let (mut stack, mut log) = stack(&["a", "b"]);
+        assert!(stack.apply(StackCommand::Replace("nope"), &mut log).is_err());
+        assert!(stack.apply(StackCommand::ClearAndPush("nope"), &mut log).is_err());
+        assert!(stack.apply(StackCommand::Push("nope"), &mut log).is_err());
+        assert!(stack.apply(StackCommand::PopTo("c"), &mut log).is_err());
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.top(), Some("b"));
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn pop_to_stops_at_the_named_state() {
+        let (mut stack, mut log) = stack(&["a", "b", "c"]);
+        stack.apply(StackCommand::PopTo("a"), &mut log).unwrap();
+        assert_eq!(stack.top(), Some("a"));
+        assert_eq!(log, vec!["exit c", "exit b", "uncover a"]);
+    }
+
+    #[test]
+    fn the_last_state_is_never_popped() {
+        let (mut stack, mut log) = stack(&["a", "b"]);
+        stack.apply(StackCommand::Pop(5), &mut log).unwrap();
+        assert_eq!(stack.top(), Some("a"));
+        stack.apply(StackCommand::Pop(1), &mut log).unwrap();
+        assert_eq!(stack.top(), Some("a"));
+        assert_eq!(log, vec!["exit b", "uncover a"]);
+    }
+
+    #[test]
+    fn replace_and_clear_swap_states() {
+        let (mut stack, mut log) = stack(&["a", "b"]);
+        stack.apply(StackCommand::Replace("c"), &mut log).unwrap();
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.top(), Some("c"));
+        stack.apply(StackCommand::ClearAndPush("b"), &mut log).unwrap();
+        assert_eq!(stack.len(), 1);
+        assert_eq!(stack.top(), Some("b"));
+        assert_eq!(log, vec!["exit b", "exit c", "exit a"]);
+    }
+}
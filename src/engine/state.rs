@@ -1,10 +1,42 @@
-use sdl2::render::Renderer;
+use engine::render::RenderBackend;
 
 pub trait StateT {
     type Message;
     type EngineData;
 
     fn process_message(&mut self, &mut Self::EngineData, Self::Message) -> Option<Self::Message>;
-    fn render(&mut self, &mut Renderer, &mut Self::EngineData);
+    fn render(&mut self, &mut RenderBackend, &mut Self::EngineData);
     fn is_fullscreen(&self) -> bool;
+
+    /// Called by the engine right after the state is pushed on the stack
+    fn on_enter(&mut self, _data: &mut Self::EngineData) {}
+    /// Called by the engine right after the state is removed from the stack
+    fn on_exit(&mut self, _data: &mut Self::EngineData) {}
+    /// Called when another state is pushed on top of this one
+    fn on_cover(&mut self, _data: &mut Self::EngineData) {}
+    /// Called when this state becomes the topmost one again
+    fn on_uncover(&mut self, _data: &mut Self::EngineData) {}
+    /// Called on every state on the stack after fonts or settings were reloaded from disk,
+    /// e.g. to measure texts again
+    fn on_reload(&mut self, _data: &mut Self::EngineData) {}
+
+    /// Runs a developer console command split into words; states that do not know the
+    /// command return `None` so that it is offered to the states below
+    fn console_command(&mut self,
+                       _data: &mut Self::EngineData,
+                       _args: &[&str])
+                       -> Option<String> {
+        None
+    }
+
+    /// Name of the state type, shown by the debug overlay
+    fn debug_name(&self) -> &'static str {
+        "State"
+    }
+    /// Extra lines for the debug overlay, e.g. entity counts
+    fn debug_details(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Draws debugging aids over the state while the debug overlay is shown
+    fn render_debug(&mut self, _r: &mut RenderBackend, _data: &mut Self::EngineData) {}
 }
@@ -0,0 +1,152 @@
+/// Loudness of a tone unless set otherwise, leaves headroom for a few sounds at once
+pub const DEFAULT_VOLUME: f32 = 0.3;
+/// State the noise generator starts from, so that the same tones always render the same
+const NOISE_SEED: u16 = 1;
+
+/// Shape of a tone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wave {
+    /// Square wave that is high for the given fraction (the duty cycle) of every period
+    Square(f32),
+    /// Pseudo-random levels, picking a new one `frequency` times a second
+    Noise,
+}
+
+/// A tone sliding from one frequency to another, fading out until it ends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    pub wave: Wave,
+    /// Frequency at the start and at the end, in Hz
+    pub from: f32,
+    pub to: f32,
+    pub millis: u32,
+    /// Level at the start, from 0.0 to 1.0
+    pub volume: f32,
+}
+
+impl Tone {
+    /// Square wave with a 50% duty cycle
+    pub fn square(from: f32, to: f32, millis: u32) -> Tone {
+        Tone {
+            wave: Wave::Square(0.5),
+            from: from,
+            to: to,
+            millis: millis,
+            volume: DEFAULT_VOLUME,
+        }
+    }
+
+    pub fn noise(frequency: f32, millis: u32) -> Tone {
+        Tone {
+            wave: Wave::Noise,
+            from: frequency,
+            to: frequency,
+            millis: millis,
+            volume: DEFAULT_VOLUME,
+        }
+    }
+
+    /// Thinner (lower `duty`) square waves sound more nasal
+    pub fn with_duty(mut self, duty: f32) -> Tone {
+        self.wave = Wave::Square(duty);
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Tone {
+        self.volume = volume;
+        self
+    }
+
+    /// Number of samples the tone lasts at `rate` samples per second
+    pub fn sample_count(&self, rate: i32) -> usize {
+        (rate as u64 * self.millis as u64 / 1000) as usize
+    }
+}
+
+/// Renders the tones one after the other into samples between -1.0 and 1.0, `rate` per
+/// second. Needs no audio device, and the same tones always give the same samples.
+pub fn render(tones: &[Tone], rate: i32) -> Vec<f32> {
+    let mut samples = Vec::with_capacity(tones.iter().map(|tone| tone.sample_count(rate)).sum());
+    let mut noise = NOISE_SEED;
+    for tone in tones {
+        let length = tone.sample_count(rate);
+        let mut phase = 0.0f32;
+        for i in 0..length {
+            let progress = i as f32 / length as f32;
+            let frequency = tone.from + (tone.to - tone.from) * progress;
+            phase += frequency / rate as f32;
+            let high = match tone.wave {
+                Wave::Square(duty) => {
+                    phase %= 1.0;
+                    phase < duty
+                }
+                Wave::Noise => {
+                    // a new level every time the phase wraps around
+                    while phase >= 1.0 {
+                        phase -= 1.0;
+                        noise = next_noise(noise);
+                    }
+                    noise & 1 == 1
+                }
+            };
+            let level = if high { tone.volume } else { -tone.volume };
+            samples.push(level * (1.0 - progress));
+        }
+    }
+    samples
+}
+
+/// Plays sample buffers at the same time, the result is as long as the longest one
+pub fn mix(buffers: &[Vec<f32>]) -> Vec<f32> {
+    let length = buffers.iter().map(|buffer| buffer.len()).max().unwrap_or(0);
+    let mut samples = vec![0.0; length];
+    for buffer in buffers {
+        for (sample, value) in samples.iter_mut().zip(buffer) {
+            *sample += *value;
+        }
+    }
+    for sample in &mut samples {
+        *sample = sample.max(-1.0).min(1.0);
+    }
+    samples
+}
+
+/// 15 bit linear feedback shift register, like the noise channel of old consoles
+fn next_noise(state: u16) -> u16 {
+    let feedback = (state ^ (state >> 1)) & 1;
+    (state >> 1) | (feedback << 14)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_lasts_the_tones_millis() {
+        let tones = [Tone::square(440.0, 440.0, 100), Tone::noise(2000.0, 50)];
+        assert_eq!(render(&tones[..1], 22050).len(), 2205);
+        assert_eq!(render(&tones, 22050).len(), 2205 + 1102);
+        assert_eq!(render(&tones, 8000).len(), 800 + 400);
+        assert!(render(&[], 22050).is_empty());
+    }
+
+    #[test]
+    fn render_is_deterministic() {
+        let tones = [Tone::square(880.0, 440.0, 60).with_duty(0.25), Tone::noise(3000.0, 40)];
+        assert_eq!(render(&tones, 22050), render(&tones, 22050));
+        assert!(render(&tones, 22050).iter().all(|s| s.abs() <= DEFAULT_VOLUME));
+    }
+
+    #[test]
+    fn mix_is_as_long_as_the_longest_buffer() {
+        let mixed = mix(&[vec![0.25; 3], vec![0.5; 5]]);
+        assert_eq!(mixed, vec![0.75, 0.75, 0.75, 0.5, 0.5]);
+        assert!(mix(&[]).is_empty());
+    }
+
+    #[test]
+    fn mix_clamps() {
+        let mixed = mix(&[vec![0.75, -0.75], vec![0.5, -0.5]]);
+        assert_eq!(mixed, vec![1.0, -1.0]);
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use sdl2::keyboard::Keycode;
+
+use engine::message::EngineEvent;
+use log::Category;
+
+/// Terminals only report key presses (and auto-repeat), so a key counts as released
+/// when nothing was received for it for this long, in milliseconds. It has to be longer
+/// than the usual auto-repeat delay.
+const RELEASE_TIMEOUT: u32 = 550;
+/// An escape byte nothing followed for this long, in milliseconds, is the Escape key rather
+/// than the start of an escape sequence split between reads
+const ESCAPE_TIMEOUT: u32 = 50;
+
+/// Runs `stty` on the controlling terminal with the given arguments
+fn stty(args: &[&str]) -> io::Result<String> {
+    let tty = File::open("/dev/tty")?;
+    let output = Command::new("stty").args(args).stdin(Stdio::from(tty)).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, "stty failed"))
+    }
+}
+
+/// Number of columns and rows of the controlling terminal, 80x24 if it can not be found out
+pub fn terminal_size() -> (u32, u32) {
+    stty(&["size"])
+        .ok()
+        .and_then(|size| {
+            let mut parts = size.split_whitespace().map(|x| x.parse::<u32>().ok());
+            match (parts.next(), parts.next()) {
+                (Some(Some(rows)), Some(Some(columns))) if rows > 0 && columns > 0 => {
+                    Some((columns, rows))
+                }
+                _ => None,
+            }
+        })
+        .unwrap_or((80, 24))
+}
+
+/// Keyboard input read from a terminal in raw mode
+/// Bytes from stdin are read on a separate thread and translated into the same
+/// `EngineEvent::ButtonPressed`/`EngineEvent::ButtonReleased` SDL keyboard events become.
+pub struct TerminalInput {
+    bytes: Receiver<u8>,
+    /// Keys that are currently considered held, with the time they were last seen
+    held: HashMap<Keycode, u32>,
+    /// Bytes of an escape sequence that has not been received completely yet
+    pending: Vec<u8>,
+    /// When bytes were last received
+    last_received: u32,
+    /// Terminal settings to restore on drop
+    saved_mode: Option<String>,
+}
+
+impl TerminalInput {
+    pub fn new() -> TerminalInput {
+        let saved_mode = stty(&["-g"]).ok();
+        if let Err(e) = stty(&["raw", "-echo"]) {
+            error!(Category::Input, "Could not switch the terminal to raw mode: {}", e);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            for byte in stdin.lock().bytes() {
+                match byte {
+                    Ok(b) => {
+                        if sender.send(b).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        TerminalInput {
+            bytes: receiver,
+            held: HashMap::new(),
+            pending: Vec::new(),
+            last_received: 0,
+            saved_mode: saved_mode,
+        }
+    }
+
+    /// Returns events for everything typed since the last call; `now` is the current time
+    /// in milliseconds and is used to release keys that are no longer repeated
+    pub fn poll<A>(&mut self, now: u32) -> Vec<EngineEvent<A>> {
+        let mut received = false;
+        while let Ok(b) = self.bytes.try_recv() {
+            self.pending.push(b);
+            received = true;
+        }
+        if received {
+            self.last_received = now;
+        }
+
+        let complete = now.wrapping_sub(self.last_received) > ESCAPE_TIMEOUT;
+        let (keys, used) = translate(&self.pending, complete);
+        self.pending.drain(..used);
+        let mut messages = Vec::new();
+        for key in keys {
+            match key {
+                TerminalKey::Interrupt => messages.push(EngineEvent::Exit),
+                TerminalKey::Key(k) => {
+                    if self.held.insert(k, now).is_none() {
+                        messages.push(EngineEvent::ButtonPressed(k));
+                    }
+                }
+            }
+        }
+
+        let released: Vec<Keycode> = self.held
+            .iter()
+            .filter(|&(_, &seen)| now.wrapping_sub(seen) > RELEASE_TIMEOUT)
+            .map(|(k, _)| *k)
+            .collect();
+        for k in released {
+            self.held.remove(&k);
+            messages.push(EngineEvent::ButtonReleased(k));
+        }
+
+        messages
+    }
+}
+
+impl Drop for TerminalInput {
+    fn drop(&mut self) {
+        let restored = match self.saved_mode {
+            Some(ref mode) => stty(&[mode.as_str()]),
+            None => stty(&["sane"]),
+        };
+        if let Err(e) = restored {
+            error!(Category::Input, "Could not restore the terminal mode: {}", e);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum TerminalKey {
+    Key(Keycode),
+    /// Ctrl-C, which does not raise SIGINT in raw mode
+    Interrupt,
+}
+
+/// Translates raw terminal bytes into keys, returns them along with the number of bytes used.
+/// `ESC [ A`..`ESC [ D` are the arrows and a lone escape byte is the Escape key, but unless
+/// the input is `complete` an escape byte at the end is left unused, as the rest of its
+/// sequence may not have been received yet.
+fn translate(bytes: &[u8], complete: bool) -> (Vec<TerminalKey>, usize) {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x1b if i + 2 < bytes.len() && (bytes[i + 1] == b'[' || bytes[i + 1] == b'O') => {
+                match bytes[i + 2] {
+                    b'A' => keys.push(TerminalKey::Key(Keycode::Up)),
+                    b'B' => keys.push(TerminalKey::Key(Keycode::Down)),
+                    b'C' => keys.push(TerminalKey::Key(Keycode::Right)),
+                    b'D' => keys.push(TerminalKey::Key(Keycode::Left)),
+                    _ => {}
+                }
+                i += 3;
+                continue;
+            }
+            0x1b if !complete && (i + 1 == bytes.len() ||
+                                  (i + 2 == bytes.len() &&
+                                   (bytes[i + 1] == b'[' || bytes[i + 1] == b'O'))) => break,
+            0x1b => keys.push(TerminalKey::Key(Keycode::Escape)),
+            0x03 => keys.push(TerminalKey::Interrupt),
+            b'\r' | b'\n' => keys.push(TerminalKey::Key(Keycode::Return)),
+            b' ' => keys.push(TerminalKey::Key(Keycode::Space)),
+            0x7f | 0x08 => keys.push(TerminalKey::Key(Keycode::Backspace)),
+            b if (b as char).is_alphanumeric() && b < 0x80 => {
+                let name = (b as char).to_uppercase().collect::<String>();
+                if let Some(k) = Keycode::from_name(&name) {
+                    keys.push(TerminalKey::Key(k));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    (keys, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Keycode;
+
+    use super::{translate, TerminalKey};
+
+    fn keys(codes: &[Keycode]) -> Vec<TerminalKey> {
+        codes.iter().map(|&k| TerminalKey::Key(k)).collect()
+    }
+
+    #[test]
+    fn bytes_become_keys() {
+        assert_eq!(translate(b"w1 \r\x7f", false),
+                   (keys(&[Keycode::W,
+                           Keycode::Num1,
+                           Keycode::Space,
+                           Keycode::Return,
+                           Keycode::Backspace]),
+                    5));
+        assert_eq!(translate(b"\x03", false), (vec![TerminalKey::Interrupt], 1));
+    }
+
+    #[test]
+    fn escape_sequences_are_arrows() {
+        assert_eq!(translate(b"\x1b[A\x1b[B\x1bOC\x1b[D", false),
+                   (keys(&[Keycode::Up, Keycode::Down, Keycode::Right, Keycode::Left]), 12));
+    }
+
+    #[test]
+    fn split_escape_sequences_wait_for_the_rest() {
+        assert_eq!(translate(b"a\x1b", false), (keys(&[Keycode::A]), 1));
+        assert_eq!(translate(b"a\x1b[", false), (keys(&[Keycode::A]), 1));
+        assert_eq!(translate(b"\x1b[A", false), (keys(&[Keycode::Up]), 3));
+    }
+
+    #[test]
+    fn lone_escape_is_escape_once_complete() {
+        assert_eq!(translate(b"\x1b", true), (keys(&[Keycode::Escape]), 1));
+        assert_eq!(translate(b"\x1bq", false), (keys(&[Keycode::Escape, Keycode::Q]), 2));
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// How often the watched files are checked, in milliseconds
+const POLL_INTERVAL: u32 = 500;
+
+/// File watcher
+/// Polls the modification times of a set of files, so that assets and settings can be
+/// reloaded while the game runs. Files that do not exist yet are watched too: creating one
+/// (e.g. an override in an earlier resource directory) counts as a change.
+pub struct FileWatcher {
+    /// Last seen modification time of every watched file, `None` if it did not exist
+    files: HashMap<PathBuf, Option<SystemTime>>,
+    last_poll: u32,
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher {
+            files: HashMap::new(),
+            last_poll: 0,
+        }
+    }
+
+    /// Starts watching `path`; the file as it is now is not reported as changed. Watching an
+    /// already watched file forgets about its pending changes.
+    pub fn watch<P: Into<PathBuf>>(&mut self, path: P) {
+        let path = path.into();
+        let modified = modified(&path);
+        self.files.insert(path, modified);
+    }
+
+    /// Files created, changed or removed since they were last checked; `now` is the time in
+    /// milliseconds, the files are only checked every `POLL_INTERVAL`
+    pub fn poll(&mut self, now: u32) -> Vec<PathBuf> {
+        if now.wrapping_sub(self.last_poll) < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = now;
+
+        let mut changed = Vec::new();
+        for (path, last_modified) in &mut self.files {
+            let modified = modified(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use testing::temp_path;
+    use super::{FileWatcher, POLL_INTERVAL};
+
+    #[test]
+    fn changes_are_reported_once() {
+        let path = temp_path("watch-changed");
+        File::create(&path).and_then(|mut file| file.write_all(b"size 10")).unwrap();
+        let mut watcher = FileWatcher::new();
+        watcher.watch(path.clone());
+        assert_eq!(watcher.poll(POLL_INTERVAL), Vec::<PathBuf>::new());
+
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        let modified = file.metadata().and_then(|metadata| metadata.modified()).unwrap();
+        file.set_modified(modified + Duration::from_secs(10)).unwrap();
+        // not checked again before the interval is over
+        assert_eq!(watcher.poll(POLL_INTERVAL + 1), Vec::<PathBuf>::new());
+        assert_eq!(watcher.poll(POLL_INTERVAL * 2), vec![path.clone()]);
+        assert_eq!(watcher.poll(POLL_INTERVAL * 3), Vec::<PathBuf>::new());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn created_and_removed_files_are_changes() {
+        let path = temp_path("watch-created");
+        let _ = fs::remove_file(&path);
+        let mut watcher = FileWatcher::new();
+        watcher.watch(path.clone());
+        File::create(&path).unwrap();
+        assert_eq!(watcher.poll(POLL_INTERVAL), vec![path.clone()]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(watcher.poll(POLL_INTERVAL * 2), vec![path.clone()]);
+        assert_eq!(watcher.poll(POLL_INTERVAL * 3), Vec::<PathBuf>::new());
+    }
+}
@@ -0,0 +1,145 @@
+use sdl2::keyboard::Keycode;
+
+use msg::{Action, Msg};
+use engine::input::InputMap;
+use game::state::pixel::GameState;
+
+/// Headless simulation
+/// Steps a `GameState` without any SDL window, renderer or fonts, so gameplay can be
+/// exercised on machines without a display (CI, balance sweeps etc.)
+pub struct HeadlessGame {
+    pub state: GameState,
+    /// Size of the playing field, same meaning as `EngineData::window_size`
+    pub bounds: (u32, u32),
+    /// Every message that fell through the game state (i.e. would have reached the engine),
+    /// except for the `Msg::Tick` passthrough, and the `Msg::Sound`s the game made
+    pub outbox: Vec<Msg>,
+    /// Bindings the buttons sent with `press` and `release` are translated with
+    pub input: InputMap<Action>,
+}
+
+impl HeadlessGame {
+    pub fn new(bounds: (u32, u32)) -> HeadlessGame {
+        HeadlessGame {
+            state: GameState::new(),
+            bounds: bounds,
+            outbox: Vec::new(),
+            input: InputMap::new(),
+        }
+    }
+
+    /// Same as `new`, but spawns are reproducible for a given `seed`
+    pub fn with_seed(bounds: (u32, u32), seed: u32) -> HeadlessGame {
+        HeadlessGame {
+            state: GameState::with_seed(seed),
+            bounds: bounds,
+            outbox: Vec::new(),
+            input: InputMap::new(),
+        }
+    }
+
+    /// Feeds a single message to the game state, buttons translated to actions like the
+    /// engine does, and returns whatever the state produced
+    pub fn send(&mut self, msg: Msg) -> Option<Msg> {
+        let msg = self.input.translate(msg);
+        let result = self.state.simulate(self.bounds, msg);
+        for sound in self.state.take_sounds() {
+            self.outbox.push(Msg::Sound(sound));
+        }
+        match result {
+            Some(Msg::Tick(_)) | None => {}
+            Some(x) => self.outbox.push(x),
+        }
+        result
+    }
+
+    pub fn tick(&mut self, dt: u32) -> Option<Msg> {
+        self.send(Msg::Tick(dt))
+    }
+
+    pub fn press(&mut self, k: Keycode) -> Option<Msg> {
+        self.send(Msg::ButtonPressed(k))
+    }
+
+    pub fn release(&mut self, k: Keycode) -> Option<Msg> {
+        self.send(Msg::ButtonReleased(k))
+    }
+
+    /// Sends up to `ticks` ticks of `dt` milliseconds each, stopping early at the first
+    /// message other than `Msg::Tick` (e.g. the stack command showing the game over screen)
+    /// which is then returned
+    pub fn run(&mut self, dt: u32, ticks: u32) -> Option<Msg> {
+        for _ in 0..ticks {
+            match self.tick(dt) {
+                Some(Msg::Tick(_)) | None => {}
+                Some(x) => return Some(x),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Keycode;
+
+    use msg::{Msg, Sound};
+    use super::HeadlessGame;
+
+    /// Plays a fixed set of inputs, returns where the player ended up and the outbox
+    fn play(seed: u32) -> (f32, f32, f32, String) {
+        let mut game = HeadlessGame::with_seed((640, 480), seed);
+        game.run(20, 25);
+        game.press(Keycode::Right);
+        game.run(20, 40);
+        game.press(Keycode::Down);
+        game.release(Keycode::Right);
+        game.run(20, 40);
+        game.release(Keycode::Down);
+        game.run(20, 200);
+        let player = game.state.player();
+        (player.x, player.y, player.size, format!("{:?}", game.outbox))
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        assert_eq!(play(42), play(42));
+        assert_eq!(play(7), play(7));
+    }
+
+    #[test]
+    fn bound_keys_move_the_player() {
+        let mut game = HeadlessGame::with_seed((640, 480), 1);
+        let start = game.state.player().x;
+        game.press(Keycode::D);
+        game.run(20, 10);
+        assert!(game.state.player().x > start);
+    }
+
+    #[test]
+    fn edibles_fit_the_field() {
+        let mut game = HeadlessGame::with_seed((100, 80), 3);
+        for _ in 0..1000 {
+            game.tick(20);
+            for edible in game.state.edibles() {
+                assert!(edible.rect.right() <= 100 && edible.rect.bottom() <= 80,
+                        "{:?} is off the field",
+                        edible.rect);
+            }
+        }
+    }
+
+    #[test]
+    fn edibles_spawn_on_fields_narrower_than_them() {
+        // less than the 10 seconds it takes for a spike to show up
+        let mut game = HeadlessGame::with_seed((10, 10), 5);
+        for _ in 0..400 {
+            game.tick(20);
+        }
+        // the player fills the whole field, so every edible gets eaten right away
+        assert!(game.outbox.iter().any(|msg| match *msg {
+            Msg::Sound(Sound::Eat { .. }) => true,
+            _ => false,
+        }));
+    }
+}
@@ -1 +1,14 @@
 pub mod state;
+pub mod headless;
+pub mod screens;
+pub mod sounds;
+
+use msg::Msg;
+use game::state::pixel::GameSettings;
+
+/// The engine as Hungry Pixel runs it, with its messages and settings
+pub type Engine = ::engine::Engine<Msg, GameSettings>;
+/// Data shared by the Hungry Pixel states
+pub type EngineData = ::engine::data::EngineData<Msg, GameSettings>;
+/// Replays of Hungry Pixel games, see `Engine::start_playback`
+pub type Replay = ::engine::replay::Replay<Msg, GameSettings>;
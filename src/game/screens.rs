@@ -0,0 +1,146 @@
+use rand;
+
+use msg::{Msg, StackCommand};
+use log::Category;
+use engine::error::EngineError;
+use engine::font::RenderableText;
+use engine::registry::StateRegistry;
+use engine::state::StateT;
+use game::{sounds, Engine, EngineData};
+use game::state::pixel::GameState;
+use game::state::menu::{MenuState, MenuPosition};
+use game::state::static_string::StaticState;
+use game::state::options::OptionsState;
+use game::state::display_options::DisplayOptionsState;
+
+/// Names the game states are registered under
+pub const INTRO: &'static str = "intro";
+pub const MAIN_MENU: &'static str = "main_menu";
+pub const GAME: &'static str = "game";
+pub const GAME_MENU: &'static str = "game_menu";
+pub const OPTIONS: &'static str = "options";
+pub const DISPLAY: &'static str = "display";
+pub const GAME_OVER: &'static str = "game_over";
+pub const WIN: &'static str = "win";
+pub const CREDITS: &'static str = "credits";
+/// All of the above, so that states can be named in the console
+pub const STATE_NAMES: [&'static str; 9] = [INTRO, MAIN_MENU, GAME, GAME_MENU, OPTIONS, DISPLAY,
+                                            GAME_OVER, WIN, CREDITS];
+
+type BoxedState = Box<StateT<Message = Msg, EngineData = EngineData>>;
+
+/// Fonts used by the states, as (key, file, size)
+const FONTS: [(&'static str, &'static str, u16); 2] = [("default", "PressStart2P-Regular.ttf", 14),
+                                                       ("default-large",
+                                                        "PressStart2P-Regular.ttf",
+                                                        24)];
+
+/// Description of the sprites the entities are drawn with
+const SPRITES: &'static str = "sprites.cfg";
+
+/// Sets Hungry Pixel up on `engine`: loads the fonts and sprites, makes the sounds,
+/// registers the states and shows the intro.
+/// Fails if a font can not be loaded, since none of the screens can be drawn without them.
+pub fn start(engine: &mut Engine) -> Result<(), EngineError> {
+    for &(key, file, size) in FONTS.iter() {
+        engine.load_font(key, file, size)?;
+    }
+    if let Err(e) = engine.load_sprites(SPRITES) {
+        warn!(Category::Engine, "{}, drawing with solid colors instead", e);
+    }
+    engine.sounds = Some(sounds::samples);
+    register_states(&mut engine.states.registry);
+    engine.push_state(INTRO);
+    Ok(())
+}
+
+/// Registers all the Hungry Pixel states
+pub fn register_states(registry: &mut StateRegistry<Msg, EngineData>) {
+    registry.register(INTRO, intro_screen);
+    registry.register(MAIN_MENU, main_menu);
+    registry.register(GAME, game);
+    registry.register(GAME_MENU, in_game_menu);
+    registry.register(OPTIONS, options);
+    registry.register(DISPLAY, display_options);
+    registry.register(GAME_OVER, gameover_screen);
+    registry.register(WIN, winning_screen);
+    registry.register(CREDITS, credits);
+}
+
+fn game(ed: &mut EngineData) -> BoxedState {
+    let seed = match ed.seed {
+        Some(seed) => seed,
+        None => rand::random(),
+    };
+    Box::new(GameState::with_settings(seed, ed.game_settings.clone()))
+}
+
+fn in_game_menu(_: &mut EngineData) -> BoxedState {
+    let choices = vec![("Resume", Msg::Stack(StackCommand::Pop(1))),
+                       ("To Main Menu", Msg::Stack(StackCommand::ClearAndPush(MAIN_MENU)))];
+
+    Box::new(MenuState::new(choices,
+                            Some(Msg::Stack(StackCommand::Pop(1))),
+                            MenuPosition::Centered,
+                            Some("PAUSE"),
+                            false))
+}
+
+fn main_menu(_: &mut EngineData) -> BoxedState {
+    let choices = vec![("New Game", Msg::Stack(StackCommand::Push(GAME))),
+                       ("Controls", Msg::Stack(StackCommand::Push(OPTIONS))),
+                       ("Display", Msg::Stack(StackCommand::Push(DISPLAY))),
+                       ("Credits", Msg::Stack(StackCommand::ClearAndPush(CREDITS))),
+                       ("Exit Game", Msg::Exit)];
+
+    Box::new(MenuState::new(choices,
+                            None,
+                            MenuPosition::Centered,
+                            Some("HUNGRY PIXEL"),
+                            true))
+}
+
+fn intro_screen(_: &mut EngineData) -> BoxedState {
+    let lines = vec!["This is a game about a pixel who is very hungry.",
+                     "So he eats...",
+                     "And eats...",
+                     "He eats so much that he grows into a square!.."];
+    Box::new(StaticState::new("default",
+                              lines,
+                              1000,
+                              Msg::Stack(StackCommand::ClearAndPush(MAIN_MENU))))
+}
+
+fn gameover_screen(_: &mut EngineData) -> BoxedState {
+    let lines = vec!["GAME OVER", "Unfortunately."];
+    Box::new(StaticState::new("default-large",
+                              lines,
+                              1000,
+                              Msg::Stack(StackCommand::ClearAndPush(MAIN_MENU))))
+}
+
+fn winning_screen(_: &mut EngineData) -> BoxedState {
+    let lines = vec!["Congratulations!", "You've won!"];
+    Box::new(StaticState::new("default-large",
+                              lines,
+                              1000,
+                              Msg::Stack(StackCommand::ClearAndPush(CREDITS))))
+}
+
+fn credits(_: &mut EngineData) -> BoxedState {
+    let lines = vec![RenderableText::from("Author:"),
+                     RenderableText::from("Crazy-Owl"),
+                     RenderableText::with_color("http://GitHub.com/Crazy-Owl", (0, 255, 0, 0))];
+    Box::new(StaticState::new("default-large",
+                              lines,
+                              1500,
+                              Msg::Stack(StackCommand::ClearAndPush(MAIN_MENU))))
+}
+
+fn options(_: &mut EngineData) -> BoxedState {
+    Box::new(OptionsState::new())
+}
+
+fn display_options(_: &mut EngineData) -> BoxedState {
+    Box::new(DisplayOptionsState::new())
+}
@@ -0,0 +1,83 @@
+use msg::Sound;
+use engine::audio::SAMPLE_RATE;
+use engine::synth::{self, Tone};
+
+/// Pitch of the player at its starting size, in Hz; bigger players sound lower
+const BASE_PITCH: f32 = 880.0;
+const START_SIZE: f32 = 20.0;
+
+/// Samples of the sound at `SAMPLE_RATE`, made from square waves and noise
+pub fn samples(sound: Sound) -> Vec<f32> {
+    let samples = synth::render(&tones(sound), SAMPLE_RATE);
+    match sound {
+        Sound::Hit { .. } => {
+            let crunch = [Tone::noise(4000.0, 60), Tone::noise(1500.0, 120).with_volume(0.2)];
+            synth::mix(&[synth::render(&crunch, SAMPLE_RATE), samples])
+        }
+        _ => samples,
+    }
+}
+
+/// Tones of the sound, played one after the other; a hit also has a crunch of noise over them
+pub fn tones(sound: Sound) -> Vec<Tone> {
+    match sound {
+        Sound::Eat { nutrition, size } => {
+            // a blip, then a tone rising more and for longer the more nutritious the edible was
+            let pitch = pitch(size);
+            let rise = 1.0 + nutrition.max(0.0) / 25.0;
+            let millis = (40.0 + nutrition.max(0.0) * 3.0).min(250.0) as u32;
+            vec![Tone::square(pitch, pitch, 30).with_duty(0.25),
+                 Tone::square(pitch, pitch * rise, millis)]
+        }
+        Sound::Hit { size } => {
+            let pitch = pitch(size);
+            vec![Tone::square(pitch, pitch / 2.0, 180)]
+        }
+        Sound::Win => notes(&[523.0, 659.0, 784.0, 1047.0], 120),
+        Sound::Lose => notes(&[392.0, 330.0, 262.0, 196.0], 180),
+        Sound::MenuMove => vec![Tone::square(660.0, 660.0, 30).with_duty(0.125)],
+        Sound::MenuSelect => {
+            vec![Tone::square(880.0, 880.0, 40).with_duty(0.25),
+                 Tone::square(1320.0, 1320.0, 60).with_duty(0.25)]
+        }
+    }
+}
+
+/// Pitch for a player of `size`, an octave lower every time it quadruples
+pub fn pitch(size: f32) -> f32 {
+    let pitch = BASE_PITCH * (START_SIZE / size.max(1.0)).sqrt();
+    pitch.max(110.0).min(1760.0)
+}
+
+fn notes(pitches: &[f32], millis: u32) -> Vec<Tone> {
+    pitches.iter().map(|&pitch| Tone::square(pitch, pitch, millis)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pitch_falls_as_size_grows() {
+        assert_eq!(pitch(START_SIZE), BASE_PITCH);
+        assert!(pitch(40.0) < pitch(20.0));
+        assert!(pitch(80.0) < pitch(40.0));
+        assert_eq!(pitch(10000.0), 110.0);
+        assert_eq!(pitch(0.0), 1760.0);
+    }
+
+    #[test]
+    fn nutritious_edibles_sound_longer_and_rise_more() {
+        let rise = |nutrition: f32| {
+            let tones = tones(Sound::Eat { nutrition: nutrition, size: 20.0 });
+            let tone = tones[tones.len() - 1];
+            (tone.millis, tone.to / tone.from)
+        };
+        let (short, low) = rise(2.0);
+        let (long, high) = rise(20.0);
+        assert!(long > short);
+        assert!(high > low);
+        let eat = |nutrition: f32| samples(Sound::Eat { nutrition: nutrition, size: 20.0 });
+        assert!(eat(20.0).len() > eat(2.0).len());
+    }
+}
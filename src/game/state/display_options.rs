@@ -0,0 +1,72 @@
+use game::state::menu::{MenuState, MenuPosition};
+use msg::{DisplayCommand, Msg, StackCommand};
+use game::EngineData;
+use engine::display::DisplaySettings;
+use engine::state::StateT;
+use engine::render::RenderBackend;
+
+/// Fullscreen, scaling and auto pause settings screen
+/// The settings are changed by the engine, the menu items are updated once it is done
+pub struct DisplayOptionsState {
+    menu: MenuState,
+    /// Settings the menu items currently show
+    shown: Option<DisplaySettings>,
+}
+
+impl DisplayOptionsState {
+    pub fn new() -> DisplayOptionsState {
+        let choices = vec![("Fullscreen", Msg::Display(DisplayCommand::ToggleFullscreen)),
+                           ("Mode", Msg::Display(DisplayCommand::NextFullscreenMode)),
+                           ("Scaling", Msg::Display(DisplayCommand::NextScaling)),
+                           ("Auto pause", Msg::Display(DisplayCommand::TogglePauseOnFocusLoss)),
+                           ("Back", Msg::Stack(StackCommand::Pop(1)))];
+
+        let menu = MenuState::new(choices,
+                                  Some(Msg::Stack(StackCommand::Pop(1))),
+                                  MenuPosition::Centered,
+                                  Some("Display"),
+                                  true);
+        DisplayOptionsState {
+            menu: menu,
+            shown: None,
+        }
+    }
+
+    pub fn update_items(&mut self, display: DisplaySettings) {
+        let fullscreen = if display.fullscreen { "On" } else { "Off" };
+        self.menu.change_item_text(0, format!("{:<12}{}", "Fullscreen", fullscreen));
+        self.menu.change_item_text(1, format!("{:<12}{}", "Mode", display.fullscreen_mode));
+        self.menu.change_item_text(2, format!("{:<12}{}", "Scaling", display.scaling));
+        let auto_pause = if display.pause_on_focus_loss { "On" } else { "Off" };
+        self.menu.change_item_text(3, format!("{:<12}{}", "Auto pause", auto_pause));
+        self.shown = Some(display);
+    }
+}
+
+impl StateT for DisplayOptionsState {
+    type Message = Msg;
+    type EngineData = EngineData;
+
+    fn process_message(&mut self, ed: &mut EngineData, msg: Msg) -> Option<Msg> {
+        self.menu.process_message(ed, msg)
+    }
+
+    fn render(&mut self, r: &mut RenderBackend, ed: &mut EngineData) {
+        if self.shown != Some(ed.display) {
+            self.update_items(ed.display);
+        }
+        self.menu.render(r, ed);
+    }
+
+    fn is_fullscreen(&self) -> bool {
+        true
+    }
+
+    fn debug_name(&self) -> &'static str {
+        "DisplayOptionsState"
+    }
+
+    fn on_reload(&mut self, ed: &mut EngineData) {
+        self.menu.on_reload(ed);
+    }
+}
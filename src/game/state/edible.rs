@@ -1,5 +1,5 @@
 use rand::Rng;
-use rand;
+use sdl2::pixels::Color::{self, RGB};
 use sdl2::rect::Rect;
 
 pub struct Edible {
@@ -15,6 +15,16 @@ impl Edible {
         }
     }
 
+    /// Name of the sprite the edible is drawn with
+    pub fn sprite(&self) -> &'static str {
+        "edible"
+    }
+
+    /// Color the edible is drawn with when its sprite is missing
+    pub fn color(&self) -> Color {
+        RGB(255, 128, 0)
+    }
+
     pub fn deteriorate(&mut self, x: f32) {
         if self.nutrition < x {
             self.nutrition = 0.0;
@@ -24,18 +34,31 @@ impl Edible {
         self.rect.resize(self.nutrition as u32, self.nutrition as u32);
     }
 
-    pub fn random(x_max: u32, y_max: u32, nut_range_min: f32, nut_range_max: f32) -> Edible {
-
-        let mut rng = rand::thread_rng();
-
+    /// Edible of a nutrition between the given ones, placed left of `x_max` and above
+    /// `y_max`; the room it takes up has to be left out of them
+    pub fn random<R: Rng>(rng: &mut R,
+                          x_max: u32,
+                          y_max: u32,
+                          nut_range_min: f32,
+                          nut_range_max: f32)
+                          -> Edible {
         let nutrition = rng.gen_range(nut_range_min, nut_range_max);
 
         Edible {
-            rect: Rect::new(rng.gen_range(0, x_max as i32 - nutrition as i32),
-                            rng.gen_range(0, y_max as i32 - nutrition as i32),
+            rect: Rect::new(coordinate(rng, x_max),
+                            coordinate(rng, y_max),
                             nutrition as u32,
                             nutrition as u32),
             nutrition: nutrition,
         }
     }
 }
+
+/// Coordinate below `max`, or 0 when there is no room: `gen_range` panics on empty ranges
+fn coordinate<R: Rng>(rng: &mut R, max: u32) -> i32 {
+    if max == 0 {
+        0
+    } else {
+        rng.gen_range(0, max as i32)
+    }
+}
@@ -1,12 +1,12 @@
-use sdl2::render::{Texture, Renderer};
-use sdl2::keyboard::Keycode;
 use sdl2::rect::Rect;
 use sdl2::pixels::Color::*;
 
-use msg::Msg;
-use engine::data::EngineData;
+use msg::{Action, Movement, Msg, Sound};
+use game::EngineData;
 use engine::state::StateT;
-use engine::font::FontCache;
+use engine::font::RenderableText;
+use engine::render::RenderBackend;
+use engine::error::OrLog;
 use std::cmp;
 
 pub enum MenuPosition {
@@ -15,12 +15,21 @@ pub enum MenuPosition {
 }
 
 struct MenuItem {
-    text: String,
-    texture: Texture,
+    text: RenderableText,
     dimensions: (u32, u32),
     msg: Msg,
 }
 
+impl MenuItem {
+    fn new<T: Into<String>>(text: T, msg: Msg) -> MenuItem {
+        MenuItem {
+            text: RenderableText::new(text),
+            dimensions: (0, 0),
+            msg: msg,
+        }
+    }
+}
+
 pub struct MenuState {
     menu_items: Vec<MenuItem>,
     currently_selected: i8,
@@ -32,71 +41,44 @@ pub struct MenuState {
     is_dirty: bool,
 }
 
-impl<'m, 'b> MenuState {
-    pub fn new<T: Into<String>>(r: &mut Renderer,
-                                font_cache: &mut FontCache,
-                                choices: Vec<(T, Msg)>,
+impl MenuState {
+    pub fn new<T: Into<String>>(choices: Vec<(T, Msg)>,
                                 on_escape: Option<Msg>,
                                 position: MenuPosition,
                                 decoration_parameters: Option<(T)>,
                                 is_fullscreen: bool)
                                 -> MenuState {
-        let mut menu_items = Vec::new();
-        let mut max_width: u32 = 0;
-        let mut max_height: u32 = 0;
-        for choice in choices {
-            let text = choice.0.into();
-            let texture = font_cache.render_texture(r, "default", text.as_str()).unwrap();
-            let query = texture.query();
-            menu_items.push(MenuItem {
-                text: text,
-                texture: texture,
-                dimensions: (query.width, query.height),
-                msg: choice.1,
-            });
-            if query.width > max_width {
-                max_width = query.width;
-            }
-            max_height += query.height;
-        }
-        let decoration_item = if let Some(s) = decoration_parameters {
-            let text = s.into();
-            let texture = font_cache.render_texture(r, "default", text.as_str()).unwrap();
-            let query = texture.query();
-            Some(MenuItem {
-                text: text,
-                texture: texture,
-                dimensions: (query.width, query.height),
-                msg: Msg::NoOp,
-            })
-        } else {
-            None
-        };
+        let menu_items = choices.into_iter().map(|(text, msg)| MenuItem::new(text, msg)).collect();
+        let decoration_item = decoration_parameters.map(|s| MenuItem::new(s, Msg::NoOp));
         MenuState {
             menu_items: menu_items,
             currently_selected: 0,
-            dimensions: (max_width, max_height),
+            dimensions: (0, 0),
             on_escape: on_escape,
             position: position,
             decoration: decoration_item,
             is_fullscreen: is_fullscreen,
-            is_dirty: false, // TODO
+            // dimensions are only known once there is something to measure the text with
+            is_dirty: true,
         }
     }
 
-    pub fn rerender_menu_items(&mut self, r: &mut Renderer, fc: &mut FontCache) {
+    pub fn rerender_menu_items(&mut self, r: &mut RenderBackend) {
+        let mut max_height: u32 = 0;
         for menu_item in &mut self.menu_items {
-            menu_item.texture = fc.render_texture(r, "default", menu_item.text.as_str()).unwrap();
-            let query = menu_item.texture.query();
-            menu_item.dimensions = (query.width, query.height);
+            menu_item.dimensions = r.text_size("default", menu_item.text.text())
+                .or_log()
+                .unwrap_or((0, 0));
+            max_height += menu_item.dimensions.1;
         }
 
         if let Some(ref mut decoration) = self.decoration {
-            decoration.texture = fc.render_texture(r, "default", decoration.text.as_str()).unwrap();
-            let query = decoration.texture.query();
-            decoration.dimensions = (query.width, query.height);
+            decoration.dimensions = r.text_size("default", decoration.text.text())
+                .or_log()
+                .unwrap_or((0, 0));
         }
 
+        self.dimensions.1 = max_height;
         self.resize();
 
         self.is_dirty = false;
@@ -114,28 +96,39 @@ impl<'m, 'b> MenuState {
 
     pub fn change_item_text<T: Into<String>>(&mut self, idx: usize, new_text: T) {
         let item: &mut MenuItem = &mut self.menu_items[idx];
-        item.text = new_text.into();
+        item.text = RenderableText::new(new_text);
         self.is_dirty = true;
     }
 
-    pub fn process_button(&mut self, k: Keycode) -> Option<Msg> {
-        match k {
-            Keycode::Up => {
+    /// Moves the selection or picks an item, with a sound for each
+    pub fn process_action(&mut self, ed: &mut EngineData, action: Action) -> Option<Msg> {
+        match action {
+            Action::Move(Movement::Up) => {
                 self.currently_selected -= 1;
                 if self.currently_selected < 0 {
                     self.currently_selected = self.menu_items.len() as i8 - 1;
                 }
+                ed.send(Msg::Sound(Sound::MenuMove));
                 None
             }
-            Keycode::Down => {
+            Action::Move(Movement::Down) => {
                 self.currently_selected += 1;
                 if self.currently_selected > self.menu_items.len() as i8 - 1 {
                     self.currently_selected = 0;
                 }
+                ed.send(Msg::Sound(Sound::MenuMove));
                 None
             }
-            Keycode::Return => Some(self.menu_items[self.currently_selected as usize].msg),
-            Keycode::Escape => self.on_escape,
+            Action::Confirm => {
+                ed.send(Msg::Sound(Sound::MenuSelect));
+                Some(self.menu_items[self.currently_selected as usize].msg)
+            }
+            Action::Back => {
+                if self.on_escape.is_some() {
+                    ed.send(Msg::Sound(Sound::MenuSelect));
+                }
+                self.on_escape
+            }
             _ => None,
         }
     }
@@ -155,18 +148,22 @@ impl StateT for MenuState {
     type Message = Msg;
     type EngineData = EngineData;
 
-    fn process_message(&mut self, _: &mut EngineData, msg: Msg) -> Option<Msg> {
+    fn process_message(&mut self, ed: &mut EngineData, msg: Msg) -> Option<Msg> {
         match msg {
             Msg::Tick(_) |
-            Msg::ButtonReleased(_) => None,
-            Msg::ButtonPressed(keycode) => self.process_button(keycode),
+            Msg::ButtonPressed(_) |
+            Msg::ButtonReleased(_) |
+            Msg::ControllerPressed(_) |
+            Msg::ControllerReleased(_) |
+            Msg::ActionReleased(_) => None,
+            Msg::ActionPressed(action) => self.process_action(ed, action),
             msg => Some(msg),
         }
     }
 
-    fn render(&mut self, r: &mut Renderer, ed: &mut EngineData) {
+    fn render(&mut self, r: &mut RenderBackend, ed: &mut EngineData) {
         if self.is_dirty {
-            self.rerender_menu_items(r, &mut ed.font_cache);
+            self.rerender_menu_items(r);
         }
         let mut current_y: u32 = match self.position {
             MenuPosition::Centered => (ed.window_size.1 / 2) - (self.dimensions.1 / 2),
@@ -177,29 +174,19 @@ impl StateT for MenuState {
             MenuPosition::Pos(x, _) => x,
         };
         if let Some(ref it) = self.decoration {
-            r.copy(&it.texture,
-                      None,
-                      Some(Rect::new((ed.window_size.0 / 2 - it.dimensions.0 / 2) as i32,
-                                     (current_y - it.dimensions.1) as i32,
-                                     it.dimensions.0,
-                                     it.dimensions.1)))
-                .unwrap();
+            r.draw_text("default",
+                           &it.text,
+                           (ed.window_size.0 / 2 - it.dimensions.0 / 2) as i32,
+                           (current_y - it.dimensions.1) as i32)
+                .or_log();
         }
         for (running_counter, item) in self.menu_items.iter().enumerate() {
-            r.copy(&item.texture,
-                      None,
-                      Some(Rect::new(x as i32,
-                                     current_y as i32,
-                                     item.dimensions.0,
-                                     item.dimensions.1)))
-                .unwrap();
+            r.draw_text("default", &item.text, x as i32, current_y as i32)
+                .or_log();
             if running_counter == self.currently_selected as usize {
-                r.set_draw_color(RGB(255, 255, 255));
-                r.fill_rect(Some(Rect::new(x as i32 - 20,
-                                              current_y as i32,
-                                              15,
-                                              item.dimensions.1)))
-                    .unwrap();
+                r.fill_rect(Rect::new(x as i32 - 20, current_y as i32, 15, item.dimensions.1),
+                               RGB(255, 255, 255))
+                    .or_log();
             }
             current_y += item.dimensions.1 + 2;
         }
@@ -208,4 +195,68 @@ impl StateT for MenuState {
     fn is_fullscreen(&self) -> bool {
         self.is_fullscreen
     }
+
+    fn debug_name(&self) -> &'static str {
+        "MenuState"
+    }
+
+    fn on_reload(&mut self, _: &mut EngineData) {
+        // the font may have changed, so the items have to be measured again
+        self.is_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::rect::Rect;
+    use sdl2::pixels::Color::RGB;
+
+    use msg::{Action, Movement, Msg};
+    use game::EngineData;
+    use engine::font::ColorMod;
+    use engine::render::software::{DrawCall, SoftwareRenderer};
+    use engine::state::StateT;
+    use super::{MenuPosition, MenuState};
+
+    fn text(s: &str, x: i32, y: i32) -> DrawCall {
+        DrawCall::Text("default".to_string(), s.to_string(), ColorMod::default(), x, y)
+    }
+
+    #[test]
+    fn items_are_centered_with_a_marker_on_the_selection() {
+        let mut ed = EngineData::new();
+        let mut r = SoftwareRenderer::new(1024, 768).with_font("default", (10, 20));
+        let mut menu = MenuState::new(vec![("Start", Msg::NoOp), ("Exit", Msg::Exit)],
+                                      None,
+                                      MenuPosition::Centered,
+                                      Some("Menu"),
+                                      true);
+        // 50x40 of items in the middle of the window, the title above them
+        menu.render(&mut r, &mut ed);
+        assert_eq!(r.take_calls(),
+                   vec![text("Menu", 492, 344),
+                        text("Start", 487, 364),
+                        DrawCall::FillRect(Rect::new(467, 364, 15, 20), RGB(255, 255, 255)),
+                        text("Exit", 487, 386)]);
+
+        menu.process_message(&mut ed, Msg::ActionPressed(Action::Move(Movement::Down)));
+        menu.render(&mut r, &mut ed);
+        assert_eq!(r.take_calls()[3],
+                   DrawCall::FillRect(Rect::new(467, 386, 15, 20), RGB(255, 255, 255)));
+    }
+
+    #[test]
+    fn items_can_be_placed() {
+        let mut ed = EngineData::new();
+        let mut r = SoftwareRenderer::new(1024, 768).with_font("default", (10, 20));
+        let mut menu = MenuState::new(vec![("Back", Msg::NoOp)],
+                                      None,
+                                      MenuPosition::Pos(100, 50),
+                                      None,
+                                      false);
+        menu.render(&mut r, &mut ed);
+        assert_eq!(r.take_calls(),
+                   vec![text("Back", 100, 50),
+                        DrawCall::FillRect(Rect::new(80, 50, 15, 20), RGB(255, 255, 255))]);
+    }
 }
@@ -4,4 +4,5 @@ pub mod edible;
 pub mod menu;
 pub mod static_string;
 pub mod options;
+pub mod display_options;
 pub mod spike;
\ No newline at end of file
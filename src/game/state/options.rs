@@ -1,36 +1,37 @@
 use game::state::menu::{MenuState, MenuPosition};
-use game::state::pixel::MOVEMENT_MAPPING;
-use msg::{Movement, Msg};
-use engine::data::EngineData;
+use msg::{Action, Movement, Msg, StackCommand};
+use game::EngineData;
+use engine::input::{Input, InputMap};
 use engine::state::StateT;
-use engine::font::FontCache;
+use engine::font::RenderableText;
+use engine::render::RenderBackend;
+use engine::error::OrLog;
+use log::Category;
 
-use sdl2::render::{Renderer, Texture};
-use sdl2::keyboard::Keycode;
-use sdl2::rect::Rect;
+/// Directions in the order of the menu items
+const MOVEMENTS: [Movement; 4] = [Movement::Up, Movement::Down, Movement::Left, Movement::Right];
 
 pub struct OptionsState {
     menu: MenuState,
-    message: Texture,
+    message: RenderableText,
     current_receiver: Option<Movement>,
     is_dirty: bool,
 }
 
 impl OptionsState {
-    pub fn new(font_cache: &mut FontCache, r: &mut Renderer) -> OptionsState {
+    pub fn new() -> OptionsState {
         let choices = vec![("Up    Up", Msg::OptionsSelect(Movement::Up)),
                            ("Down  Down", Msg::OptionsSelect(Movement::Down)),
                            ("Left  Left", Msg::OptionsSelect(Movement::Left)),
-                           ("Right Right", Msg::OptionsSelect(Movement::Right))];
+                           ("Right Right", Msg::OptionsSelect(Movement::Right)),
+                           ("Reset key bindings", Msg::OptionsReset)];
 
-        let menu = MenuState::new(r,
-                                  font_cache,
-                                  choices,
-                                  Some(Msg::PopState(1)),
+        let menu = MenuState::new(choices,
+                                  Some(Msg::Stack(StackCommand::Pop(1))),
                                   MenuPosition::Centered,
                                   Some("Options"),
                                   true);
-        let message = font_cache.render_texture(r, "default", "Press new control").unwrap();
+        let message = RenderableText::new("Press new control");
         OptionsState {
             menu: menu,
             message: message,
@@ -39,41 +40,37 @@ impl OptionsState {
         }
     }
 
-    pub fn update_mappings(&mut self) {
-        let movement_map = MOVEMENT_MAPPING.lock().unwrap();
-        for &(idx, format, m) in
-            &[(0, "Up", Movement::Up),
-              (1, "Down", Movement::Down),
-              (2, "Left", Movement::Left),
-              (3, "Right", Movement::Right)] {
-            for (key, value) in movement_map.iter() {
-                if *value == m {
-                    let new_string = format!("{:<6}{}", format, key);
-                    self.menu.change_item_text(idx, new_string);
-                }
-            }
+    pub fn update_mappings(&mut self, input: &InputMap<Action>) {
+        for (idx, &m) in MOVEMENTS.iter().enumerate() {
+            let keys: Vec<String> = input.inputs(Action::Move(m))
+                .iter()
+                .map(|input| input.to_string())
+                .collect();
+            let new_string = format!("{:<6}{}", m.to_string(), keys.join(" "));
+            self.menu.change_item_text(idx, new_string);
         }
         self.is_dirty = false;
     }
 
-    pub fn remap_key(&mut self, m: Movement, k: Keycode) -> Option<Msg> {
-        let mut movement_map = MOVEMENT_MAPPING.lock().unwrap();
-
-        let mut remove_keys: Vec<Keycode> = vec![];
-
-        for (key, value) in movement_map.iter() {
-            if *value == m || *key == k {
-                remove_keys.push(*key);
+    /// Makes `bound` the only key, or controller input, moving in direction `m`. Inputs
+    /// bound to anything but movement are not taken away: the menu could not be left
+    /// without them.
+    pub fn remap_key(&mut self,
+                     input: &mut InputMap<Action>,
+                     m: Movement,
+                     bound: Input)
+                     -> Option<Msg> {
+        match input.action(bound) {
+            Some(Action::Move(_)) | None => {
+                input.rebind(Action::Move(m), bound);
+                self.is_dirty = true;
+                Some(Msg::OptionsChanged)
+            }
+            Some(action) => {
+                info!(Category::Input, "{} is used for {}", bound, action);
+                None
             }
         }
-
-        for key in &remove_keys {
-            movement_map.remove(key);
-        }
-
-        movement_map.insert(k, m);
-        self.is_dirty = true;
-        None
     }
 }
 
@@ -84,47 +81,146 @@ impl StateT for OptionsState {
     fn process_message(&mut self, ed: &mut EngineData, msg: Msg) -> Option<Msg> {
         match msg {
             Msg::Tick(_) |
-            Msg::ButtonReleased(_) => None,
+            Msg::ButtonReleased(_) |
+            Msg::ControllerReleased(_) |
+            Msg::ActionReleased(_) => None,
             Msg::ButtonPressed(keycode) if self.current_receiver.is_some() => {
-                Some(Msg::OptionsSet(keycode))
+                self.process_message(ed, Msg::OptionsSet(Input::Key(keycode)))
+            }
+            Msg::ControllerPressed(input) if self.current_receiver.is_some() => {
+                self.process_message(ed, Msg::OptionsSet(input))
+            }
+            Msg::ButtonPressed(_) |
+            Msg::ControllerPressed(_) |
+            Msg::ActionPressed(_) => {
+                // the options messages of the items mean nothing to the engine or the states
+                // below, so they are handled right here
+                match self.menu.process_message(ed, msg) {
+                    Some(picked) => self.process_message(ed, picked),
+                    None => None,
+                }
             }
-            Msg::ButtonPressed(_) => self.menu.process_message(ed, msg),
             Msg::OptionsSelect(movement) => {
                 self.current_receiver = Some(movement);
+                // the key comes untranslated, whatever it is bound to
+                ed.input.listening = true;
                 None
             }
-            Msg::OptionsSet(keycode) => {
-                if let Some(movement) = self.current_receiver {
-                    self.current_receiver = None;
-                    self.remap_key(movement, keycode)
-                } else {
-                    None
+            Msg::OptionsReset => {
+                ed.input = InputMap::new();
+                self.is_dirty = true;
+                Some(Msg::OptionsChanged)
+            }
+            Msg::OptionsSet(bound) => {
+                ed.input.listening = false;
+                match self.current_receiver.take() {
+                    // going back cancels
+                    Some(_) if ed.input.action(bound) == Some(Action::Back) => None,
+                    Some(movement) => self.remap_key(&mut ed.input, movement, bound),
+                    None => None,
                 }
             }
             msg => Some(msg),
         }
     }
 
-    fn render(&mut self, r: &mut Renderer, ed: &mut EngineData) {
+    fn render(&mut self, r: &mut RenderBackend, ed: &mut EngineData) {
         if self.is_dirty {
-            self.update_mappings();
+            self.update_mappings(&ed.input);
         }
         self.menu.render(r, ed);
         if self.current_receiver.is_some() {
-            let message_query = self.message.query();
-            r.copy(&self.message,
-                      None,
-                      Some(Rect::new((ed.window_size.0 / 2 - message_query.width / 2) as i32,
-                                     (ed.window_size.1 / 2 + self.menu.get_dimensions().1 / 2 +
-                                      message_query.height * 2) as
-                                     i32,
-                                     message_query.width,
-                                     message_query.height)))
-                .unwrap();
+            let message_size = r.text_size("default", self.message.text())
+                .or_log()
+                .unwrap_or((0, 0));
+            r.draw_text("default",
+                           &self.message,
+                           (ed.window_size.0 / 2 - message_size.0 / 2) as i32,
+                           (ed.window_size.1 / 2 + self.menu.get_dimensions().1 / 2 +
+                            message_size.1 * 2) as i32)
+                .or_log();
         }
     }
 
     fn is_fullscreen(&self) -> bool {
         true
     }
+
+    fn debug_name(&self) -> &'static str {
+        "OptionsState"
+    }
+
+    fn on_exit(&mut self, ed: &mut EngineData) {
+        ed.input.listening = false;
+    }
+
+    fn on_reload(&mut self, ed: &mut EngineData) {
+        self.menu.on_reload(ed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Keycode;
+    use sdl2::controller::{Axis, Button};
+
+    use msg::{Action, Movement, Msg};
+    use game::EngineData;
+    use engine::input::{Input, InputMap};
+    use engine::state::StateT;
+    use super::OptionsState;
+
+    #[test]
+    fn picked_items_are_handled_by_the_options() {
+        let mut ed = EngineData::new();
+        let mut options = OptionsState::new();
+        // the first item waits for a key for moving up
+        match options.process_message(&mut ed, Msg::ActionPressed(Action::Confirm)) {
+            None => {}
+            msg => panic!("Expected the pick to be handled, got {:?}", msg),
+        }
+        assert!(ed.input.listening);
+
+        // and going back cancels it
+        match options.process_message(&mut ed, Msg::ButtonPressed(Keycode::Escape)) {
+            None => {}
+            msg => panic!("Expected the key to be handled, got {:?}", msg),
+        }
+        assert!(!ed.input.listening);
+        assert_eq!(ed.input.action(Input::Key(Keycode::Escape)), Some(Action::Back));
+        assert_eq!(ed.input, InputMap::new());
+    }
+
+    #[test]
+    fn a_picked_direction_is_bound_to_the_next_key() {
+        let mut ed = EngineData::new();
+        let mut options = OptionsState::new();
+        options.process_message(&mut ed, Msg::ActionPressed(Action::Confirm));
+        match options.process_message(&mut ed, Msg::ButtonPressed(Keycode::I)) {
+            Some(Msg::OptionsChanged) => {}
+            msg => panic!("Expected the options to change, got {:?}", msg),
+        }
+        assert!(!ed.input.listening);
+        // the key replaces the keys, the controller inputs stay
+        assert_eq!(ed.input.inputs(Action::Move(Movement::Up)),
+                   vec![Input::Key(Keycode::I),
+                        Input::Button(Button::DPadUp),
+                        Input::Axis(Axis::LeftY, false)]);
+        assert_eq!(ed.input.action(Input::Key(Keycode::W)), None);
+    }
+
+    #[test]
+    fn the_last_item_resets_the_bindings() {
+        let mut ed = EngineData::new();
+        ed.input.rebind(Action::Move(Movement::Up), Input::Key(Keycode::I));
+        ed.input.unbind(Input::Key(Keycode::P));
+        let mut options = OptionsState::new();
+        // up from the first item wraps around to the last
+        options.process_message(&mut ed, Msg::ActionPressed(Action::Move(Movement::Up)));
+        match options.process_message(&mut ed, Msg::ActionPressed(Action::Confirm)) {
+            Some(Msg::OptionsChanged) => {}
+            msg => panic!("Expected the options to change, got {:?}", msg),
+        }
+        assert_eq!(ed.input, InputMap::new());
+    }
 }
@@ -1,28 +1,39 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-use sdl2::render::Renderer;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::fmt::Display;
+use std::str::FromStr;
+use rand::{self, SeedableRng, StdRng};
 use sdl2::pixels::Color::*;
-use sdl2::keyboard::Keycode;
+use sdl2::rect::{Point, Rect};
 
 use engine::state::StateT;
-use msg::{Msg, Movement, GameCommand, MenuMsg};
-use engine::data::EngineData;
+use msg::{Msg, Movement, GameCommand, StackCommand, Sound, Action};
+use engine::data::SettingsT;
+use engine::font::RenderableText;
+use engine::render::RenderBackend;
+use engine::error::OrLog;
+use game::EngineData;
+use game::screens::{GAME_MENU, GAME_OVER, WIN};
+use log::Category;
 use super::player::Player;
 use super::edible::Edible;
 use super::spike::Spike;
 
-lazy_static! {
-    pub static ref MOVEMENT_MAPPING: Mutex<HashMap<Keycode, Movement>> = {
-        let mut hm = HashMap::new();
-        // TODO: do something with these invocations, probably a macro use?
-        hm.insert(Keycode::Up, Movement::Up);
-        hm.insert(Keycode::Down, Movement::Down);
-        hm.insert(Keycode::Left, Movement::Left);
-        hm.insert(Keycode::Right, Movement::Right);
-        Mutex::new(hm)
-    };
-}
+/// Names of all `GameSettings` fields, as accepted by `GameSettings::get` and `GameSettings::set`
+pub const SETTINGS_FIELDS: [&'static str; 8] = ["max_velocity",
+                                                "deterioration_rate",
+                                                "edible_deterioration_rate",
+                                                "acceleration_rate",
+                                                "edibles_spawn_rate",
+                                                "edible_bounds",
+                                                "spikes_spawn_rate",
+                                                "spikes_bounds"];
+
+/// Largest edible or spike the bounds settings allow, so that they always fit the playing field
+pub const MAX_SPAWN_SIZE: u32 = 200;
 
+#[derive(Debug, Clone)]
 pub struct GameSettings {
     pub max_velocity: f32,
     pub deterioration_rate: f32,
@@ -34,7 +45,7 @@ pub struct GameSettings {
     pub spikes_bounds: (u32, u32),
 }
 
-impl GameSettings {
+impl SettingsT for GameSettings {
     fn new() -> GameSettings {
         GameSettings {
             max_velocity: 30.0,
@@ -47,6 +58,93 @@ impl GameSettings {
             spikes_bounds: (15, 55),
         }
     }
+
+    /// Returns the value of a setting as a string, pairs are written as `a,b`
+    fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "max_velocity" => Some(self.max_velocity.to_string()),
+            "deterioration_rate" => Some(self.deterioration_rate.to_string()),
+            "edible_deterioration_rate" => Some(self.edible_deterioration_rate.to_string()),
+            "acceleration_rate" => Some(self.acceleration_rate.to_string()),
+            "edibles_spawn_rate" => Some(self.edibles_spawn_rate.to_string()),
+            "edible_bounds" => Some(format!("{},{}", self.edible_bounds.0, self.edible_bounds.1)),
+            "spikes_spawn_rate" => Some(self.spikes_spawn_rate.to_string()),
+            "spikes_bounds" => Some(format!("{},{}", self.spikes_bounds.0, self.spikes_bounds.1)),
+            _ => None,
+        }
+    }
+
+    /// Parses `value` and assigns it to the setting named `key`. Bounds have to be a lower
+    /// and a higher size of at most `MAX_SPAWN_SIZE`, others are refused.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "max_velocity" => self.max_velocity = parse_value(value)?,
+            "deterioration_rate" => self.deterioration_rate = parse_value(value)?,
+            "edible_deterioration_rate" => self.edible_deterioration_rate = parse_value(value)?,
+            "acceleration_rate" => self.acceleration_rate = parse_value(value)?,
+            "edibles_spawn_rate" => self.edibles_spawn_rate = parse_value(value)?,
+            "edible_bounds" => {
+                self.edible_bounds = check_bounds(parse_pair(value)?, MAX_SPAWN_SIZE as u8)?
+            }
+            "spikes_spawn_rate" => self.spikes_spawn_rate = parse_value(value)?,
+            "spikes_bounds" => {
+                self.spikes_bounds = check_bounds(parse_pair(value)?, MAX_SPAWN_SIZE)?
+            }
+            _ => return Err(format!("Unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Reads settings from a file with one `key value` line per setting, e.g.
+    /// `max_velocity 40`; `#` starts a comment and missing settings keep their defaults
+    fn load<P: AsRef<Path>>(path: P) -> Result<GameSettings, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut settings = GameSettings::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, char::is_whitespace);
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => {
+                    settings.set(key, value.trim())
+                        .map_err(|e| format!("line {}: {}", index + 1, e))?
+                }
+                _ => return Err(format!("line {}: expected a setting and a value", index + 1)),
+            }
+        }
+        Ok(settings)
+    }
+
+    fn fields() -> &'static [&'static str] {
+        &SETTINGS_FIELDS
+    }
+}
+
+fn parse_value<T: FromStr>(value: &str) -> Result<T, String> {
+    value.trim().parse::<T>().map_err(|_| format!("Invalid value: {}", value))
+}
+
+fn parse_pair<T: FromStr>(value: &str) -> Result<(T, T), String> {
+    let mut parts = value.split(',');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(a), Some(b), None) => Ok((parse_value(a)?, parse_value(b)?)),
+        _ => Err(format!("Expected two comma separated values: {}", value)),
+    }
+}
+
+/// Spawning picks sizes between the bounds, which fails unless the lower one is smaller.
+/// Sizes bigger than the playing field are fine: the spawns are then put at its edge.
+fn check_bounds<T: PartialOrd + Display>(bounds: (T, T), max: T) -> Result<(T, T), String> {
+    if bounds.0 >= bounds.1 {
+        Err(format!("The lower bound {} has to be smaller than {}", bounds.0, bounds.1))
+    } else if bounds.1 > max {
+        Err(format!("The higher bound {} is too big, it can be at most {}", bounds.1, max))
+    } else {
+        Ok(bounds)
+    }
 }
 
 pub struct GameState {
@@ -57,11 +155,30 @@ pub struct GameState {
     settings: GameSettings,
     spike_eta: f32,
     spikes: Vec<Spike>,
+    /// Seed `rng` was created with, shown on screen so a run can be replayed
+    seed: u32,
+    /// All the spawning goes through this generator
+    rng: StdRng,
+    /// Spikes do not hurt and the player does not starve, toggled from the console
+    god_mode: bool,
+    /// Sounds of what happened during `simulate`, see `take_sounds`
+    sounds: Vec<Sound>,
+    /// Whether the game was running when another state covered it
+    running_when_covered: bool,
 }
 
 impl GameState {
+    /// Creates a game with a random seed
     pub fn new() -> GameState {
-        let settings = GameSettings::new();
+        GameState::with_seed(rand::random())
+    }
+
+    pub fn with_seed(seed: u32) -> GameState {
+        GameState::with_settings(seed, GameSettings::new())
+    }
+
+    pub fn with_settings(seed: u32, settings: GameSettings) -> GameState {
+        info!(Category::Game, "New game with seed {}", seed);
         GameState {
             running: true,
             player: Player::new(),
@@ -70,18 +187,37 @@ impl GameState {
             spike_eta: settings.spikes_spawn_rate,
             settings: settings,
             spikes: Vec::new(),
+            seed: seed,
+            rng: StdRng::from_seed(&[seed as usize]),
+            god_mode: false,
+            sounds: Vec::new(),
+            running_when_covered: true,
         }
     }
 
     pub fn spawn_edible(&mut self, max_x: u32, max_y: u32) {
-        self.edibles.push(Edible::random(max_x,
-                                         max_y,
-                                         self.settings.edible_bounds.0 as f32,
-                                         self.settings.edible_bounds.1 as f32));
+        let edible = Edible::random(&mut self.rng,
+                                    max_x,
+                                    max_y,
+                                    self.settings.edible_bounds.0 as f32,
+                                    self.settings.edible_bounds.1 as f32);
+        debug!(Category::Game,
+               "Edible of {:.1} spawned at {}, {}",
+               edible.nutrition,
+               edible.rect.x(),
+               edible.rect.y());
+        self.edibles.push(edible);
     }
 
     pub fn spawn_spike(&mut self, max_x: i32, max_y: i32, min_size: u32, max_size: u32) {
-        self.spikes.push(Spike::random(max_x, max_y, min_size, max_size));
+        let spike = Spike::random(&mut self.rng, max_x, max_y, min_size, max_size);
+        debug!(Category::Game,
+               "Spike of {}x{} spawned at {}, {}",
+               spike.rect.width(),
+               spike.rect.height(),
+               spike.rect.x(),
+               spike.rect.y());
+        self.spikes.push(spike);
     }
 
     pub fn process_game_command(&mut self, c: GameCommand) -> Option<Msg> {
@@ -118,69 +254,178 @@ impl GameState {
                 self.running = true;
                 None
             }
-            GameCommand::Menu => {
-                self.running = false;
-                Some(Msg::MenuCommand(MenuMsg::ShowGameMenu))
-            }
+            GameCommand::Menu => Some(Msg::Stack(StackCommand::Push(GAME_MENU))),
         }
     }
 
-    pub fn process_button_press(&mut self, k: Keycode) -> Option<Msg> {
-        if let Some(direction) = MOVEMENT_MAPPING.lock().unwrap().get(&k) {
-            self.process_game_command(GameCommand::StartMovement(*direction))
-        } else {
-            match k {
-                Keycode::Escape => self.process_game_command(GameCommand::Menu),
-                Keycode::P | Keycode::Pause => {
-                    let is_running = self.running;
-                    self.process_game_command({
-                        if is_running {
-                            GameCommand::Pause
-                        } else {
-                            GameCommand::Resume
-                        }
-                    })
-                }
-                _ => None,
+    pub fn process_action_press(&mut self, action: Action) -> Option<Msg> {
+        match action {
+            Action::Move(direction) => {
+                self.process_game_command(GameCommand::StartMovement(direction))
+            }
+            Action::Back => self.process_game_command(GameCommand::Menu),
+            Action::Pause => {
+                let is_running = self.running;
+                self.process_game_command({
+                    if is_running {
+                        GameCommand::Pause
+                    } else {
+                        GameCommand::Resume
+                    }
+                })
             }
+            Action::Confirm => None,
         }
     }
 
-    pub fn process_button_release(&mut self, k: Keycode) -> Option<Msg> {
-        if let Some(direction) = MOVEMENT_MAPPING.lock().unwrap().get(&k) {
-            self.process_game_command(GameCommand::StopMovement(*direction))
-        } else {
-            None
+    pub fn process_action_release(&mut self, action: Action) -> Option<Msg> {
+        match action {
+            Action::Move(direction) => {
+                self.process_game_command(GameCommand::StopMovement(direction))
+            }
+            _ => None,
         }
     }
-}
 
-impl StateT for GameState {
-    type Message = Msg;
-    type EngineData = EngineData;
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
 
-    fn process_message(&mut self,
-                       engine_data: &mut Self::EngineData,
-                       msg: Self::Message)
-                       -> Option<Self::Message> {
+    pub fn edibles(&self) -> &[Edible] {
+        &self.edibles
+    }
+
+    pub fn spikes(&self) -> &[Spike] {
+        &self.spikes
+    }
+
+    pub fn settings(&self) -> &GameSettings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut GameSettings {
+        &mut self.settings
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Runs a developer console command, see `StateT::console_command`. Settings changed
+    /// here are also stored in `game_settings`, so the next game keeps them.
+    pub fn run_console_command(&mut self,
+                               game_settings: &mut GameSettings,
+                               args: &[&str])
+                               -> Option<String> {
+        let number = |i: usize| args.get(i).and_then(|w| w.parse::<f32>().ok());
+        let output = match (args.get(0).map(|w| *w), args.len()) {
+            (Some("help"), 1) => {
+                "settings, get KEY, set KEY VALUE, spawn edible|spike X Y, size SIZE, god"
+                    .to_string()
+            }
+            (Some("settings"), 1) => {
+                SETTINGS_FIELDS.iter()
+                    .map(|key| format!("{} = {}", key, self.settings.get(key).unwrap()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            }
+            (Some("get"), 2) => {
+                match self.settings.get(args[1]) {
+                    Some(value) => format!("{} = {}", args[1], value),
+                    None => format!("Unknown setting: {}", args[1]),
+                }
+            }
+            (Some("set"), 3) => {
+                match self.settings.set(args[1], args[2]) {
+                    Ok(()) => {
+                        let _ = game_settings.set(args[1], args[2]);
+                        format!("{} = {}", args[1], self.settings.get(args[1]).unwrap())
+                    }
+                    Err(e) => e,
+                }
+            }
+            (Some("spawn"), _) => {
+                match (args.get(1).map(|w| *w), number(2), number(3)) {
+                    (Some("edible"), Some(x), Some(y)) => {
+                        let nutrition = number(4).unwrap_or(self.settings.edible_bounds.1 as f32);
+                        self.edibles.push(Edible::new(x as i32, y as i32, nutrition));
+                        format!("Edible spawned at {}, {}", x, y)
+                    }
+                    (Some("spike"), Some(x), Some(y)) => {
+                        let size = number(4).unwrap_or(self.settings.spikes_bounds.0 as f32);
+                        let speed = number(5).unwrap_or(0.0);
+                        self.spikes.push(Spike::new(x as i32,
+                                                    y as i32,
+                                                    size as u32,
+                                                    size as u32,
+                                                    (1, 0),
+                                                    speed));
+                        format!("Spike spawned at {}, {}", x, y)
+                    }
+                    _ => {
+                        "Usage: spawn edible X Y [NUTRITION] | spawn spike X Y [SIZE] [SPEED]"
+                            .to_string()
+                    }
+                }
+            }
+            (Some("size"), 2) => {
+                match number(1) {
+                    Some(size) if size > 1.0 => {
+                        let current = self.player.size;
+                        self.player.resize(size - current);
+                        format!("Player size is {}", size)
+                    }
+                    _ => "Usage: size SIZE, SIZE above 1".to_string(),
+                }
+            }
+            (Some("god"), 1) => {
+                self.god_mode = !self.god_mode;
+                format!("God mode {}", if self.god_mode { "on" } else { "off" })
+            }
+            _ => return None,
+        };
+        Some(output)
+    }
+
+    /// Sounds of the eating, hits and endings since the last call
+    pub fn take_sounds(&mut self) -> Vec<Sound> {
+        ::std::mem::replace(&mut self.sounds, Vec::new())
+    }
+
+    /// Simulation half of the state: processes a message against a playing field of the
+    /// given `bounds` without touching anything SDL video related, so it can be driven
+    /// without a window (see `game::headless`).
+    pub fn simulate(&mut self, bounds: (u32, u32), msg: Msg) -> Option<Msg> {
         match msg {
             Msg::Tick(x) => {
                 if self.running {
 
-                    if !self.player.process(x as f32, engine_data, &self.settings) {
-                        return Some(Msg::ShowGameOver);
+                    if !self.player.process(x as f32, bounds, &self.settings) {
+                        if !self.god_mode {
+                            info!(Category::Game, "Game over, the player starved");
+                            self.sounds.push(Sound::Lose);
+                            return Some(Msg::Stack(StackCommand::ClearAndPush(GAME_OVER)));
+                        }
+                        let starved = self.player.size;
+                        self.player.resize(2.0 - starved);
                     }
                     self.edible_eta -= (x as f32) / 1000.0;
                     if self.edible_eta <= 0.0 {
-                        self.spawn_edible(engine_data.window_size.0 - 25,
-                                          engine_data.window_size.1 - 25);
+                        // room for the biggest edible, so that none sticks out of the field
+                        let size = self.settings.edible_bounds.1 as u32;
+                        self.spawn_edible(bounds.0.saturating_sub(size),
+                                          bounds.1.saturating_sub(size));
                         self.edible_eta = self.settings.edibles_spawn_rate;
                     }
                     self.spike_eta -= (x as f32) / 1000.0;
                     if self.spike_eta <= 0.0 {
                         let spikes_bounds = self.settings.spikes_bounds;
-                        self.spawn_spike(engine_data.window_size.0 as i32,
-                                         engine_data.window_size.1 as i32,
+                        self.spawn_spike(bounds.0 as i32,
+                                         bounds.1 as i32,
                                          spikes_bounds.0,
                                          spikes_bounds.1);
                         self.spike_eta = self.settings.spikes_spawn_rate;
@@ -196,6 +441,10 @@ impl StateT for GameState {
                         }
                         if self.player.rect.intersection(edible.rect).is_some() {
                             self.player.size += edible.nutrition;
+                            self.sounds.push(Sound::Eat {
+                                nutrition: edible.nutrition,
+                                size: self.player.size,
+                            });
                             to_remove_edibles.push(edible_idx);
                         }
                     }
@@ -203,13 +452,16 @@ impl StateT for GameState {
                     let mut to_remove_spikes = Vec::<usize>::new();
                     for spike_idx in 0..self.spikes.len() {
                         let spike = &mut self.spikes[spike_idx];
-                        spike.update(x as f32 / 1000.0, (engine_data.window_size.0 as f32, engine_data.window_size.1 as f32));
+                        spike.update(x as f32 / 1000.0, (bounds.0 as f32, bounds.1 as f32));
                         if self.player.rect.intersection(spike.rect).is_some() {
-                            if self.player.size >= 40.0 {
-                                self.player.size -= 20.0;
-                            } else {
-                                self.player.size -= 0.5 * self.player.size;
+                            if !self.god_mode {
+                                if self.player.size >= 40.0 {
+                                    self.player.size -= 20.0;
+                                } else {
+                                    self.player.size -= 0.5 * self.player.size;
+                                }
                             }
+                            self.sounds.push(Sound::Hit { size: self.player.size });
                             to_remove_spikes.push(spike_idx);
                         }
                     }
@@ -225,43 +477,214 @@ impl StateT for GameState {
                         self.spikes.swap_remove(removing_idx);
                     }
 
-                    if self.player.size >= (engine_data.window_size.1 as f32 / 2.0) {
-                        return Some(Msg::ShowWinScreen);
+                    if self.player.size >= (bounds.1 as f32 / 2.0) {
+                        info!(Category::Game, "Won at size {:.1}", self.player.size);
+                        self.sounds.push(Sound::Win);
+                        return Some(Msg::Stack(StackCommand::ClearAndPush(WIN)));
                     }
                 }
                 Some(Msg::Tick(x))
             }
-            Msg::MenuCommand(MenuMsg::ResumeGame) => {
-                self.running = true;
-                Some(Msg::MenuCommand(MenuMsg::ResumeGame))
-            }
+            // the menu covering the game pauses it
+            Msg::FocusLost if self.running => self.process_game_command(GameCommand::Menu),
+            Msg::FocusLost | Msg::FocusGained => None,
             Msg::Command(x) => self.process_game_command(x),
             // Buttons
-            Msg::ButtonPressed(x) => self.process_button_press(x),
-            Msg::ButtonReleased(x) => self.process_button_release(x),
+            Msg::ActionPressed(x) => self.process_action_press(x),
+            Msg::ActionReleased(x) => self.process_action_release(x),
+            Msg::ButtonPressed(_) |
+            Msg::ButtonReleased(_) |
+            Msg::ControllerPressed(_) |
+            Msg::ControllerReleased(_) => None,
             Msg::NoOp => None,
             msg => Some(msg),
         }
     }
+}
 
-    fn render(&mut self, r: &mut Renderer, _: &mut EngineData) {
-        r.set_draw_color(RGB(0, 255, 0));
+impl StateT for GameState {
+    type Message = Msg;
+    type EngineData = EngineData;
+
+    fn process_message(&mut self,
+                       engine_data: &mut Self::EngineData,
+                       msg: Self::Message)
+                       -> Option<Self::Message> {
+        let result = self.simulate(engine_data.window_size, msg);
+        for sound in self.take_sounds() {
+            engine_data.send(Msg::Sound(sound));
+        }
+        result
+    }
+
+    fn render(&mut self, r: &mut RenderBackend, ed: &mut EngineData) {
         // get player left upper corner coordinates
         // TODO: proper handling, just player x, y for now
         // this will cause strange behavior, and should be eliminated
-        r.fill_rect(Some(self.player.rect))
-            .unwrap();
-        r.set_draw_color(RGB(255, 128, 0));
+        ed.sprites
+            .draw(r, self.player.sprite(), self.player.rect, self.player.color())
+            .or_log();
         for edible in &self.edibles {
-            r.fill_rect(Some(edible.rect)).unwrap();
+            ed.sprites.draw(r, edible.sprite(), edible.rect, edible.color()).or_log();
         }
-        r.set_draw_color(RGB(255, 0, 0));
         for spike in &self.spikes {
-            r.fill_rect(Some(spike.rect)).unwrap();
+            ed.sprites.draw(r, spike.sprite(), spike.rect, spike.color()).or_log();
         }
+        r.draw_text("default",
+                       &RenderableText::with_color(format!("Seed: {}", self.seed),
+                                                   (150, 150, 150, 255)),
+                       5,
+                       ed.window_size.1 as i32 - 20)
+            .or_log();
     }
 
     fn is_fullscreen(&self) -> bool {
         true
     }
+
+    fn debug_name(&self) -> &'static str {
+        "GameState"
+    }
+
+    fn console_command(&mut self, ed: &mut EngineData, args: &[&str]) -> Option<String> {
+        self.run_console_command(&mut ed.game_settings, args)
+    }
+
+    fn debug_details(&self) -> Vec<String> {
+        vec![format!("player: {:.1} at ({:.0}, {:.0}), speed ({:.0}, {:.0})",
+                     self.player.size,
+                     self.player.x,
+                     self.player.y,
+                     self.player.speed.0,
+                     self.player.speed.1),
+             format!("edibles: {}, spikes: {}", self.edibles.len(), self.spikes.len()),
+             format!("running: {}", self.running)]
+    }
+
+    fn render_debug(&mut self, r: &mut RenderBackend, _: &mut EngineData) {
+        // collision rects, and where everything will be in half a second
+        r.draw_rect(self.player.rect, RGB(255, 255, 255)).or_log();
+        draw_velocity(r, self.player.rect, self.player.speed);
+        for edible in &self.edibles {
+            r.draw_rect(edible.rect, RGB(255, 255, 255)).or_log();
+        }
+        for spike in &self.spikes {
+            r.draw_rect(spike.rect, RGB(255, 255, 255)).or_log();
+            draw_velocity(r, spike.rect, spike.velocity());
+        }
+    }
+
+    fn on_cover(&mut self, _: &mut EngineData) {
+        self.running_when_covered = self.running;
+        self.running = false;
+    }
+
+    fn on_uncover(&mut self, _: &mut EngineData) {
+        // a game paused before the menu was opened stays paused
+        self.running = self.running_when_covered;
+    }
+
+    fn on_reload(&mut self, ed: &mut EngineData) {
+        // tuned settings apply to the running game right away
+        self.settings = ed.game_settings.clone();
+    }
+}
+
+/// Draws a vector from the center of `rect` to where it moves in half a second
+fn draw_velocity(r: &mut RenderBackend, rect: Rect, velocity: (f32, f32)) {
+    let center = rect.center();
+    let to = Point::new(center.x() + (velocity.0 * 0.5) as i32,
+                        center.y() + (velocity.1 * 0.5) as i32);
+    r.draw_line(center, to, RGB(0, 255, 255)).or_log();
+}
+
+#[cfg(test)]
+mod tests {
+    use msg::{Action, Msg, Sound};
+    use engine::data::SettingsT;
+    use engine::font::ColorMod;
+    use engine::render::software::{DrawCall, SoftwareRenderer};
+    use engine::state::StateT;
+    use game::EngineData;
+    use super::{GameSettings, GameState};
+
+    #[test]
+    fn entities_are_drawn_as_rects_without_sprites() {
+        let mut ed = EngineData::new();
+        let mut r = SoftwareRenderer::new(1024, 768).with_font("default", (10, 20));
+        let mut game = GameState::with_seed(7);
+        for _ in 0..50 {
+            game.simulate(ed.window_size, Msg::Tick(20));
+        }
+        game.render(&mut r, &mut ed);
+
+        let mut expected = vec![DrawCall::FillRect(game.player().rect, game.player().color())];
+        for edible in game.edibles() {
+            expected.push(DrawCall::FillRect(edible.rect, edible.color()));
+        }
+        for spike in game.spikes() {
+            expected.push(DrawCall::FillRect(spike.rect, spike.color()));
+        }
+        expected.push(DrawCall::Text("default".to_string(),
+                                     "Seed: 7".to_string(),
+                                     ColorMod(150, 150, 150, 255),
+                                     5,
+                                     748));
+        assert_eq!(r.calls, expected);
+    }
+
+    #[test]
+    fn bounds_have_to_be_ordered_and_fit() {
+        let mut settings = GameSettings::new();
+        assert!(settings.set("spikes_bounds", "20,30").is_ok());
+        assert_eq!(settings.spikes_bounds, (20, 30));
+        for value in ["30,20", "25,25", "10,2000", "10"].iter() {
+            assert!(settings.set("spikes_bounds", value).is_err(), "{} was accepted", value);
+            assert!(settings.set("edible_bounds", value).is_err(), "{} was accepted", value);
+        }
+        assert_eq!(settings.spikes_bounds, (20, 30));
+        assert_eq!(settings.edible_bounds, GameSettings::new().edible_bounds);
+    }
+
+    #[test]
+    fn any_accepted_bounds_can_be_spawned() {
+        for &(edibles, spikes) in [("0,1", "0,1"), ("199,200", "199,200")].iter() {
+            let mut settings = GameSettings::new();
+            settings.set("edible_bounds", edibles).unwrap();
+            settings.set("spikes_bounds", spikes).unwrap();
+            let mut game = GameState::with_settings(9, settings);
+            game.god_mode = true;
+            // a field smaller than the spawns, for long enough to get spikes too
+            let mut hits = 0;
+            for _ in 0..600 {
+                game.simulate((30, 30), Msg::Tick(20));
+                hits += game.take_sounds()
+                    .iter()
+                    .filter(|sound| match **sound {
+                        Sound::Hit { .. } => true,
+                        _ => false,
+                    })
+                    .count();
+            }
+            assert!(hits + game.spikes().len() > 0,
+                    "no spikes with {} and {}",
+                    edibles,
+                    spikes);
+        }
+    }
+
+    #[test]
+    fn a_paused_game_stays_paused_under_the_menu() {
+        let mut ed = EngineData::new();
+        let mut game = GameState::with_seed(1);
+        game.on_cover(&mut ed);
+        assert!(!game.is_running());
+        game.on_uncover(&mut ed);
+        assert!(game.is_running());
+
+        game.process_action_press(Action::Pause);
+        game.on_cover(&mut ed);
+        game.on_uncover(&mut ed);
+        assert!(!game.is_running());
+    }
 }
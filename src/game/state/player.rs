@@ -1,6 +1,6 @@
+use sdl2::pixels::Color::{self, RGB};
 use sdl2::rect::Rect;
 
-use engine::data::EngineData;
 use super::pixel::GameSettings;
 
 #[derive(Debug)]
@@ -25,6 +25,16 @@ impl Player {
         }
     }
 
+    /// Name of the sprite the player is drawn with
+    pub fn sprite(&self) -> &'static str {
+        "player"
+    }
+
+    /// Color the player is drawn with when its sprite is missing
+    pub fn color(&self) -> Color {
+        RGB(0, 255, 0)
+    }
+
     pub fn set_x(&mut self, x: f32) {
         self.x = x;
         self.rect.set_x(x as i32);
@@ -47,12 +57,8 @@ impl Player {
     }
 
     // Boolean result of this fn tells whether the player has not yet lost the game
-    // (`true` means "continue")
-    pub fn process(&mut self,
-                   x: f32,
-                   engine_data: &mut EngineData,
-                   settings: &GameSettings)
-                   -> bool {
+    // (`true` means "continue"). `bounds` is the size of the playing field.
+    pub fn process(&mut self, x: f32, bounds: (u32, u32), settings: &GameSettings) -> bool {
         let offset_args = (self.speed.0 * x / 1000.0, self.speed.1 * x / 1000.0);
         self.offset(offset_args.0, offset_args.1);
         self.resize(-settings.deterioration_rate * x / 1000.0);
@@ -67,8 +73,8 @@ impl Player {
             self.speed.0 = -self.speed.0;
         }
 
-        if self.x > (engine_data.window_size.0 as f32) - self.size {
-            let new_x = engine_data.window_size.0 as f32 - self.size;
+        if self.x > (bounds.0 as f32) - self.size {
+            let new_x = bounds.0 as f32 - self.size;
             self.set_x(new_x);
             self.speed.0 = -self.speed.0;
         }
@@ -78,8 +84,8 @@ impl Player {
             self.speed.1 = -self.speed.1;
         }
 
-        if self.y > (engine_data.window_size.1 as f32) - self.size {
-            let new_y = engine_data.window_size.1 as f32 - self.size;
+        if self.y > (bounds.1 as f32) - self.size {
+            let new_y = bounds.1 as f32 - self.size;
             self.set_y(new_y);
             self.speed.1 = -self.speed.1;
         }
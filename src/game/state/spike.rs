@@ -1,5 +1,7 @@
-use rand;
+use std::cmp;
+
 use rand::Rng;
+use sdl2::pixels::Color::{self, RGB};
 use sdl2::rect::Rect;
 
 #[derive(Debug)]
@@ -24,19 +26,36 @@ impl Spike {
         }
     }
 
+    /// Name of the sprite the spike is drawn with
+    pub fn sprite(&self) -> &'static str {
+        "spike"
+    }
+
+    /// Color the spike is drawn with when its sprite is missing
+    pub fn color(&self) -> Color {
+        RGB(255, 0, 0)
+    }
+
     pub fn set_speed(&mut self, speed: f32) {
         self.speed = speed;
     }
 
-    pub fn random(max_x: i32, max_y: i32, min_size: u32, max_size: u32) -> Spike {
-        let mut rng = rand::thread_rng();
+    pub fn random<R: Rng>(rng: &mut R,
+                          max_x: i32,
+                          max_y: i32,
+                          min_size: u32,
+                          max_size: u32)
+                          -> Spike {
         let alignment: usize = rng.gen_range(0, 4);
         let (size_x, size_y): (u32, u32) = (rng.gen_range(min_size, max_size), rng.gen_range(min_size, max_size));
+        // spikes bigger than the field stick out of its far side
+        let room_x = cmp::max(max_x - size_x as i32, 0);
+        let room_y = cmp::max(max_y - size_y as i32, 0);
         let (x, y): (i32, i32) = match alignment {
-            0 => (rng.gen_range(0, max_x - size_x as i32), 0),
-            1 => (max_x - size_x as i32, rng.gen_range(0, max_y - size_y as i32)),
-            2 => (rng.gen_range(0, max_x - size_x as i32), max_y - size_y as i32),
-            3 => (0, rng.gen_range(0, max_y - size_y as i32)),
+            0 => (coordinate(rng, room_x), 0),
+            1 => (room_x, coordinate(rng, room_y)),
+            2 => (coordinate(rng, room_x), room_y),
+            3 => (0, coordinate(rng, room_y)),
             _ => unimplemented!(),
         };
 
@@ -53,6 +72,11 @@ impl Spike {
         Spike::new(x, y, size_x, size_y, direction, speed)
     }
 
+    /// Current velocity in pixels per second
+    pub fn velocity(&self) -> (f32, f32) {
+        (self.speed * self.direction.0 as f32, self.speed * self.direction.1 as f32)
+    }
+
     pub fn update(&mut self, dt: f32, bounds: (f32, f32)) {
         self.x += self.speed * (self.direction.0 as f32) * dt;
         if self.x + (self.dimensions.0 as f32) >= bounds.0 {
@@ -79,3 +103,12 @@ impl Spike {
         self.rect.reposition((self.x as i32, self.y as i32));
     }
 }
+
+/// Coordinate below `max`, or 0 when there is no room: `gen_range` panics on empty ranges
+fn coordinate<R: Rng>(rng: &mut R, max: i32) -> i32 {
+    if max <= 0 {
+        0
+    } else {
+        rng.gen_range(0, max)
+    }
+}
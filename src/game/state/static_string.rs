@@ -1,49 +1,74 @@
-use sdl2::render::{Texture, Renderer};
-use sdl2::rect::Rect;
 use msg::Msg;
-use engine::data::EngineData;
+use game::EngineData;
 use engine::state::StateT;
+use engine::font::RenderableText;
+use engine::render::RenderBackend;
+use engine::error::OrLog;
+use engine::scheduler::{Delay, TimerHandle};
 
 pub struct ScreenLine {
-    texture: Texture,
+    text: RenderableText,
     dimensions: (u32, u32),
 }
 
 pub struct StaticState {
-    time_left: i32,
+    /// How long the screen can not be skipped, in milliseconds
+    pause: u32,
+    /// Timer that makes the screen skippable, scheduled when the screen is entered
+    timer: Option<TimerHandle>,
     skippable: bool,
+    font: String,
     lines: Vec<ScreenLine>,
     next_msg: Msg,
     dimensions: (u32, u32),
+    is_dirty: bool,
 }
 
 impl StaticState {
-    pub fn new(strings: Vec<Texture>, pause: u32, next: Msg) -> StaticState {
-        let mut lines: Vec<ScreenLine> = Vec::with_capacity(strings.len());
-        let mut max_width: u32 = 0;
-        let mut max_height: u32 = 0;
-
-        for texture in strings {
-            let query = texture.query();
-            max_height += query.height;
-            if query.width > max_width {
-                max_width = query.width;
-            }
-
-            lines.push(ScreenLine {
-                texture: texture,
-                dimensions: (query.width, query.height),
-            });
-        }
+    pub fn new<F: Into<String>, T: Into<RenderableText>>(font: F,
+                                                        strings: Vec<T>,
+                                                        pause: u32,
+                                                        next: Msg)
+                                                        -> StaticState {
+        let lines: Vec<ScreenLine> = strings.into_iter()
+            .map(|s| {
+                ScreenLine {
+                    text: s.into(),
+                    dimensions: (0, 0),
+                }
+            })
+            .collect();
 
         StaticState {
-            time_left: pause as i32,
+            pause: pause,
+            timer: None,
             skippable: false,
+            font: font.into(),
             lines: lines,
             next_msg: next,
-            dimensions: (max_width, max_height),
+            dimensions: (0, 0),
+            is_dirty: true,
         }
     }
+
+    /// Measures all the lines with the font of this screen
+    fn measure_lines(&mut self, r: &mut RenderBackend) {
+        let mut max_width: u32 = 0;
+        let mut max_height: u32 = 0;
+
+        for line in &mut self.lines {
+            line.dimensions = r.text_size(&self.font, line.text.text())
+                .or_log()
+                .unwrap_or((0, 0));
+            max_height += line.dimensions.1;
+            if line.dimensions.0 > max_width {
+                max_width = line.dimensions.0;
+            }
+        }
+
+        self.dimensions = (max_width, max_height);
+        self.is_dirty = false;
+    }
 }
 
 impl StateT for StaticState {
@@ -52,35 +77,36 @@ impl StateT for StaticState {
 
     fn process_message(&mut self, _: &mut EngineData, msg: Msg) -> Option<Msg> {
         match msg {
-            Msg::Tick(x) => {
-                if !self.skippable {
-                    self.time_left -= x as i32;
-                    self.skippable = self.time_left <= 0;
-                }
+            Msg::TimerFired(handle) if self.timer == Some(handle) => {
+                self.skippable = true;
                 None
             }
-            Msg::ButtonPressed(_) => {
+            Msg::ButtonPressed(_) |
+            Msg::ControllerPressed(_) |
+            Msg::ActionPressed(_) => {
                 if self.skippable {
                     Some(self.next_msg)
                 } else {
                     None
                 }
             }
-            _ => None,
+            Msg::Tick(_) |
+            Msg::ButtonReleased(_) |
+            Msg::ControllerReleased(_) |
+            Msg::ActionReleased(_) => None,
+            msg => Some(msg),
         }
     }
 
-    fn render(&mut self, r: &mut Renderer, ed: &mut EngineData) {
+    fn render(&mut self, r: &mut RenderBackend, ed: &mut EngineData) {
+        if self.is_dirty {
+            self.measure_lines(r);
+        }
         let mut current_y: u32 = (ed.window_size.1 / 2) - (self.dimensions.1 / 2);
         let x: u32 = (ed.window_size.0 / 2) - (self.dimensions.0 / 2);
         for scr_line in &self.lines {
-            r.copy(&scr_line.texture,
-                      None,
-                      Some(Rect::new(x as i32,
-                                     current_y as i32,
-                                     scr_line.dimensions.0,
-                                     scr_line.dimensions.1)))
-                .unwrap();
+            r.draw_text(&self.font, &scr_line.text, x as i32, current_y as i32)
+                .or_log();
             current_y += scr_line.dimensions.1;
         }
     }
@@ -88,4 +114,69 @@ impl StateT for StaticState {
     fn is_fullscreen(&self) -> bool {
         true
     }
+
+    fn debug_name(&self) -> &'static str {
+        "StaticState"
+    }
+
+    fn on_reload(&mut self, _: &mut EngineData) {
+        self.is_dirty = true;
+    }
+
+    fn on_enter(&mut self, ed: &mut EngineData) {
+        self.timer = Some(ed.scheduler.after_with(Delay::Millis(self.pause), Msg::TimerFired));
+    }
+
+    fn on_exit(&mut self, ed: &mut EngineData) {
+        if let Some(timer) = self.timer.take() {
+            ed.scheduler.cancel(timer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Keycode;
+
+    use msg::{AudioCommand, DisplayCommand, Msg};
+    use game::EngineData;
+    use engine::font::ColorMod;
+    use engine::render::software::{DrawCall, SoftwareRenderer};
+    use engine::state::StateT;
+    use super::StaticState;
+
+    #[test]
+    fn lines_are_centered_with_the_screen_font() {
+        let mut ed = EngineData::new();
+        let mut r = SoftwareRenderer::new(1024, 768).with_font("big", (10, 20));
+        let mut screen = StaticState::new("big", vec!["Game over", "Press a key"], 0, Msg::NoOp);
+        screen.render(&mut r, &mut ed);
+        // 110x40 of lines in the middle of the window
+        let line = |s: &str, y: i32| {
+            DrawCall::Text("big".to_string(), s.to_string(), ColorMod::default(), 457, y)
+        };
+        assert_eq!(r.calls, vec![line("Game over", 364), line("Press a key", 384)]);
+    }
+
+    #[test]
+    fn hotkey_messages_are_passed_on() {
+        let mut ed = EngineData::new();
+        let mut screen = StaticState::new("big", vec!["Game over"], 0, Msg::NoOp);
+        match screen.process_message(&mut ed, Msg::Display(DisplayCommand::ToggleFullscreen)) {
+            Some(Msg::Display(DisplayCommand::ToggleFullscreen)) => {}
+            msg => panic!("Expected the display command back, got {:?}", msg),
+        }
+        // the game over and win screens are where muting matters most
+        let commands = [AudioCommand::VolumeUp, AudioCommand::VolumeDown, AudioCommand::ToggleMute];
+        for &command in commands.iter() {
+            match screen.process_message(&mut ed, Msg::Audio(command)) {
+                Some(Msg::Audio(back)) => assert_eq!(back, command),
+                msg => panic!("Expected the audio command back, got {:?}", msg),
+            }
+        }
+        match screen.process_message(&mut ed, Msg::ButtonReleased(Keycode::F11)) {
+            None => {}
+            msg => panic!("Expected the release to be consumed, got {:?}", msg),
+        }
+    }
 }
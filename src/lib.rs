@@ -3,9 +3,14 @@ extern crate lazy_static;
 extern crate sdl2;
 extern crate rand;
 
+#[macro_use]
+pub mod log;
 pub mod resources;
+pub mod cli;
 pub mod game;
 pub mod engine;
-mod msg;
+pub mod msg;
+#[cfg(test)]
+mod testing;
 
 pub use engine::{Engine, TEngine};
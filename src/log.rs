@@ -0,0 +1,358 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable the log levels are read from, e.g. `HUNGRY_PIXEL_LOG=debug` or
+/// `HUNGRY_PIXEL_LOG=warn,input=trace` (see `Levels`)
+pub const LOG_ENV: &'static str = "HUNGRY_PIXEL_LOG";
+/// Size in bytes at which the log file is rotated
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+/// Number of rotated log files kept next to the current one (`.1` being the newest)
+const ROTATED_LOGS: u32 = 3;
+
+/// Severity of a log record, records above the configured level are dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Part of the program a log record comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Engine,
+    Input,
+    State,
+    Font,
+    Game,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match *self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Level, String> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!("Unknown log level: {}", s)),
+        }
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Category, String> {
+        match s.to_lowercase().as_str() {
+            "engine" => Ok(Category::Engine),
+            "input" => Ok(Category::Input),
+            "state" => Ok(Category::State),
+            "font" => Ok(Category::Font),
+            "game" => Ok(Category::Game),
+            _ => Err(format!("Unknown log category: {}", s)),
+        }
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match *self {
+            Category::Engine => "engine",
+            Category::Input => "input",
+            Category::State => "state",
+            Category::Font => "font",
+            Category::Game => "game",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Most verbose level logged for every category
+/// Written as a comma separated list of a level and `category=level` overrides, e.g.
+/// `warn,input=trace` to see every input but only warnings of the rest; the level of the
+/// other categories is `info` when left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Levels {
+    pub default: Level,
+    pub categories: Vec<(Category, Level)>,
+}
+
+impl Levels {
+    pub fn new(default: Level) -> Levels {
+        Levels {
+            default: default,
+            categories: Vec::new(),
+        }
+    }
+
+    /// Most verbose level logged for `category`
+    pub fn level(&self, category: Category) -> Level {
+        self.categories
+            .iter()
+            .rev()
+            .find(|&&(c, _)| c == category)
+            .map_or(self.default, |&(_, level)| level)
+    }
+}
+
+impl Default for Levels {
+    fn default() -> Levels {
+        Levels::new(Level::Info)
+    }
+}
+
+impl FromStr for Levels {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Levels, String> {
+        let mut levels = Levels::default();
+        for part in s.split(',').map(|part| part.trim()) {
+            match part.find('=') {
+                Some(i) => {
+                    levels.categories
+                        .push((part[..i].trim().parse()?, part[i + 1..].trim().parse()?));
+                }
+                None => levels.default = part.parse()?,
+            }
+        }
+        Ok(levels)
+    }
+}
+
+/// Log file that is moved aside once it grows over `max_size`
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> io::Result<LogFile> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+            path: path,
+            file: file,
+            size: size,
+            max_size: MAX_LOG_SIZE,
+        })
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..ROTATED_LOGS).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+struct Logger {
+    levels: Levels,
+    stderr: bool,
+    file: Option<LogFile>,
+}
+
+impl Logger {
+    fn write(&mut self, level: Level, category: Category, args: fmt::Arguments) {
+        if level > self.levels.level(category) {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| format!("{}.{:03}", d.as_secs(), d.subsec_nanos() / 1000000))
+            .unwrap_or_default();
+        let line = format!("{} {:<5} {}: {}", timestamp, level, category, args);
+        if self.stderr {
+            let _ = writeln!(io::stderr(), "{}", line);
+        }
+        let failed = match self.file {
+            Some(ref mut file) => file.write_line(&line).is_err(),
+            None => false,
+        };
+        if failed {
+            // nowhere to report it but stderr, and there is no point retrying every record
+            let _ = writeln!(io::stderr(), "Could not write the log file, file logging stopped");
+            self.file = None;
+        }
+    }
+}
+
+lazy_static! {
+    static ref LOGGER: Mutex<Logger> = Mutex::new(Logger {
+        levels: Levels::default(),
+        stderr: true,
+        file: None,
+    });
+}
+
+/// Configures logging: records up to their category's level go to stderr (unless `stderr`
+/// is off, e.g. when the terminal is used for drawing) and to the log file at `file`, if any
+pub fn init(levels: Levels, stderr: bool, file: Option<PathBuf>) -> io::Result<()> {
+    let log_file = match file {
+        Some(path) => Some(LogFile::open(path)?),
+        None => None,
+    };
+    let mut logger = LOGGER.lock().unwrap();
+    logger.levels = levels;
+    logger.stderr = stderr;
+    logger.file = log_file;
+    Ok(())
+}
+
+/// Levels set in the `HUNGRY_PIXEL_LOG` environment variable, if it is set and valid
+pub fn env_levels() -> Option<Levels> {
+    ::std::env::var(LOG_ENV).ok().and_then(|value| value.parse().ok())
+}
+
+/// Writes a record, use the `error!`, `warn!`, `info!`, `debug!` and `trace!` macros instead
+pub fn write(level: Level, category: Category, args: fmt::Arguments) {
+    LOGGER.lock().unwrap().write(level, category, args);
+}
+
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $category:expr, $($arg:tt)+) => {
+        $crate::log::write($level, $category, format_args!($($arg)+))
+    }
+}
+
+#[macro_export]
+macro_rules! error {
+    ($category:expr, $($arg:tt)+) => { log!($crate::log::Level::Error, $category, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($category:expr, $($arg:tt)+) => { log!($crate::log::Level::Warn, $category, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! info {
+    ($category:expr, $($arg:tt)+) => { log!($crate::log::Level::Info, $category, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($category:expr, $($arg:tt)+) => { log!($crate::log::Level::Debug, $category, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($category:expr, $($arg:tt)+) => { log!($crate::log::Level::Trace, $category, $($arg)+) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use testing::temp_path;
+    use super::{Category, Level, Levels, LogFile, Logger};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = temp_path(&format!("log-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn levels_are_read_per_category() {
+        assert_eq!("debug".parse::<Levels>(), Ok(Levels::new(Level::Debug)));
+        let levels: Levels = "warn, input=trace".parse().unwrap();
+        assert_eq!(levels.level(Category::Input), Level::Trace);
+        assert_eq!(levels.level(Category::Engine), Level::Warn);
+        let levels: Levels = "font=error".parse().unwrap();
+        assert_eq!(levels.level(Category::Font), Level::Error);
+        assert_eq!(levels.level(Category::Game), Level::Info);
+        assert!("loud".parse::<Levels>().is_err());
+        assert!("sound=debug".parse::<Levels>().is_err());
+    }
+
+    #[test]
+    fn records_above_the_category_level_are_not_written() {
+        let dir = temp_dir("levels");
+        let path = dir.join("game.log");
+        let mut logger = Logger {
+            levels: "warn,input=debug".parse().unwrap(),
+            stderr: false,
+            file: Some(LogFile::open(path.clone()).unwrap()),
+        };
+        logger.write(Level::Info, Category::Engine, format_args!("engine info"));
+        logger.write(Level::Warn, Category::Engine, format_args!("engine warning"));
+        logger.write(Level::Debug, Category::Input, format_args!("input debug"));
+        logger.write(Level::Trace, Category::Input, format_args!("input trace"));
+
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("WARN  engine: engine warning"));
+        assert!(lines[1].ends_with("DEBUG input: input debug"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn full_log_files_are_moved_aside() {
+        let dir = temp_dir("rotation");
+        let path = dir.join("game.log");
+        let mut file = LogFile::open(path.clone()).unwrap();
+        file.max_size = 64;
+        for i in 0..5 {
+            file.write_line(&format!("record number {} of the log", i)).unwrap();
+        }
+        // 27 bytes a record, so two fit in a file
+        assert_eq!(fs::read_to_string(&path).unwrap(), "record number 4 of the log\n");
+        assert_eq!(fs::read_to_string(file.rotated_path(1)).unwrap(),
+                   "record number 2 of the log\nrecord number 3 of the log\n");
+        assert!(file.rotated_path(2).exists());
+        assert!(!file.rotated_path(3).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
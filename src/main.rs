@@ -1,17 +1,109 @@
+#[macro_use]
 extern crate hungry_pixel_rs;
 extern crate sdl2;
 
-use hungry_pixel_rs::{Engine, TEngine};
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use hungry_pixel_rs::TEngine;
+use hungry_pixel_rs::cli::{self, CliOptions};
 use hungry_pixel_rs::engine::context::SDL2Context;
+use hungry_pixel_rs::engine::error::EngineError;
+use hungry_pixel_rs::game::{screens, Engine, Replay};
+use hungry_pixel_rs::resources;
+use hungry_pixel_rs::log::{self, Category};
 
 fn main() {
-    let sdl_context = SDL2Context::new();
-    let ttf_context = sdl2::ttf::init().unwrap();
-    let mut engine: Engine = Engine::new(sdl_context, ttf_context);
-    engine.start_game();
+    // the engine is dropped by the time `run` returns, so the terminal is back to normal
+    if !run() {
+        process::exit(1);
+    }
+}
+
+/// Runs the game until it is quit, returns whether it could be started
+fn run() -> bool {
+    let options = match CliOptions::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            let _ = writeln!(io::stderr(), "{}\n{}", e, cli::USAGE);
+            return false;
+        }
+    };
+    let levels = options.log_level.clone().or_else(log::env_levels).unwrap_or_default();
+    // in the terminal stderr would end up in the middle of the picture
+    let log_to_stderr = !options.terminal;
+    let log_file = options.log_file
+        .clone()
+        .unwrap_or_else(|| resources::get_data_path("hungry-pixel.log"));
+    if let Err(e) = log::init(levels.clone(), log_to_stderr, Some(log_file.clone())) {
+        let _ = log::init(levels, log_to_stderr, None);
+        warn!(Category::Engine, "Could not open log file {}: {}", log_file.display(), e);
+    }
+    info!(Category::Engine, "Starting with {:?}", options);
+
+    let mut engine = match create_engine(&options) {
+        Ok(engine) => engine,
+        Err(e) => {
+            startup_error(&options, e);
+            return false;
+        }
+    };
+    if let Some(ref dir) = options.screenshot_dir {
+        engine.capture.directory = dir.clone();
+    }
+    engine.capture.at_frame = options.screenshot_frame;
+    engine.capture.every = options.capture_every;
+    engine.engine_data.seed = options.seed;
+    if let Some(ref path) = options.replay {
+        match Replay::load(path) {
+            Ok(replay) => engine.start_playback(replay),
+            Err(e) => {
+                error!(Category::Engine, "Could not load replay {}: {}", path.display(), e);
+                return false;
+            }
+        }
+    }
+    if let Some(ref path) = options.record {
+        if let Err(e) = engine.start_recording(path) {
+            error!(Category::Engine,
+                   "Could not start recording to {}: {}",
+                   path.display(),
+                   e);
+            return false;
+        }
+    }
+    if let Err(e) = screens::start(&mut engine) {
+        startup_error(&options, e);
+        return false;
+    }
     'running: loop {
         if !engine.process() {
             break 'running;
         }
     }
+    true
+}
+
+fn create_engine(options: &CliOptions) -> Result<Engine, EngineError> {
+    let sdl_context = SDL2Context::new()?;
+    if options.terminal {
+        return Engine::terminal(sdl_context);
+    }
+    let ttf_context = sdl2::ttf::init().map_err(|e| EngineError::Init(e.to_string()))?;
+    let dummy_driver = env::var("SDL_VIDEODRIVER").map(|d| d == "dummy").unwrap_or(false);
+    if options.software_renderer || dummy_driver {
+        Engine::software(sdl_context, ttf_context)
+    } else {
+        Engine::new(sdl_context, ttf_context)
+    }
+}
+
+/// Reports an error that keeps the game from starting. Logging to stderr is off in the
+/// terminal, but nothing has been drawn there yet, so it is printed anyway.
+fn startup_error(options: &CliOptions, e: EngineError) {
+    error!(Category::Engine, "{}", e);
+    if options.terminal {
+        let _ = writeln!(io::stderr(), "{}", e);
+    }
 }
@@ -1,31 +1,112 @@
 use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
 
+use sdl2::controller::{Axis, Button};
 use sdl2::keyboard::Keycode;
+
+use engine::scheduler::TimerHandle;
+pub use engine::stack::StackCommand;
+pub use engine::display::DisplayCommand;
+pub use engine::audio::AudioCommand;
+use engine::input::{ActionT, Input};
+use engine::message::{EngineEvent, MessageT};
+use game::screens::STATE_NAMES;
+
 /// Message type
 #[derive(Debug, Clone, Copy)]
 pub enum Msg {
     NoOp,
     Exit,
     Tick(u32),
-    StartGame,
-    MenuCommand(MenuMsg),
     ButtonPressed(Keycode),
     ButtonReleased(Keycode),
+    /// A game controller button or stick, see `engine::controller`
+    ControllerPressed(Input),
+    ControllerReleased(Input),
+    /// An input bound to the action was pressed, see `engine::input::InputMap`
+    ActionPressed(Action),
+    ActionReleased(Action),
     Command(GameCommand),
-    PopState(usize),
-    ShowGameOver,
-    ShowWinScreen,
-    ShowCredits,
-    ShowOptions,
+    /// Changes the states stack, see `StackCommand`
+    Stack(StackCommand),
     OptionsSelect(Movement),
-    OptionsSet(Keycode),
+    OptionsSet(Input),
+    /// Puts the key bindings back to their defaults
+    OptionsReset,
+    /// The bindings or volume in `EngineData` changed, the engine applies and saves them
+    OptionsChanged,
+    /// Changes the window settings, see `DisplayCommand`
+    Display(DisplayCommand),
+    /// The window lost focus or was minimized, only sent when pausing on focus loss is on
+    FocusLost,
+    /// The window got focus back or was restored
+    FocusGained,
+    /// Sent by timers scheduled with `Scheduler::after_with`
+    TimerFired(TimerHandle),
+    /// Plays a sound effect
+    Sound(Sound),
+    /// Changes the volume, see `AudioCommand`
+    Audio(AudioCommand),
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum MenuMsg {
-    ToMainMenu,
-    ShowGameMenu,
-    ResumeGame,
+/// The engine's events are the messages of the same name
+impl MessageT for Msg {
+    type Action = Action;
+    type Sound = Sound;
+
+    fn from_event(event: EngineEvent<Action>) -> Msg {
+        match event {
+            EngineEvent::Exit => Msg::Exit,
+            EngineEvent::Tick(x) => Msg::Tick(x),
+            EngineEvent::ButtonPressed(k) => Msg::ButtonPressed(k),
+            EngineEvent::ButtonReleased(k) => Msg::ButtonReleased(k),
+            EngineEvent::ControllerPressed(input) => Msg::ControllerPressed(input),
+            EngineEvent::ControllerReleased(input) => Msg::ControllerReleased(input),
+            EngineEvent::ActionPressed(action) => Msg::ActionPressed(action),
+            EngineEvent::ActionReleased(action) => Msg::ActionReleased(action),
+            EngineEvent::FocusLost => Msg::FocusLost,
+            EngineEvent::FocusGained => Msg::FocusGained,
+            EngineEvent::TimerFired(handle) => Msg::TimerFired(handle),
+            EngineEvent::Stack(command) => Msg::Stack(command),
+            EngineEvent::Display(command) => Msg::Display(command),
+            EngineEvent::Audio(command) => Msg::Audio(command),
+            EngineEvent::OptionsChanged => Msg::OptionsChanged,
+        }
+    }
+
+    fn event(&self) -> Option<EngineEvent<Action>> {
+        let event = match *self {
+            Msg::Exit => EngineEvent::Exit,
+            Msg::Tick(x) => EngineEvent::Tick(x),
+            Msg::ButtonPressed(k) => EngineEvent::ButtonPressed(k),
+            Msg::ButtonReleased(k) => EngineEvent::ButtonReleased(k),
+            Msg::ControllerPressed(input) => EngineEvent::ControllerPressed(input),
+            Msg::ControllerReleased(input) => EngineEvent::ControllerReleased(input),
+            Msg::ActionPressed(action) => EngineEvent::ActionPressed(action),
+            Msg::ActionReleased(action) => EngineEvent::ActionReleased(action),
+            Msg::FocusLost => EngineEvent::FocusLost,
+            Msg::FocusGained => EngineEvent::FocusGained,
+            Msg::TimerFired(handle) => EngineEvent::TimerFired(handle),
+            Msg::Stack(command) => EngineEvent::Stack(command),
+            Msg::Display(command) => EngineEvent::Display(command),
+            Msg::Audio(command) => EngineEvent::Audio(command),
+            Msg::OptionsChanged => EngineEvent::OptionsChanged,
+            Msg::NoOp |
+            Msg::Command(_) |
+            Msg::OptionsSelect(_) |
+            Msg::OptionsSet(_) |
+            Msg::OptionsReset |
+            Msg::Sound(_) => return None,
+        };
+        Some(event)
+    }
+
+    fn sound(&self) -> Option<Sound> {
+        match *self {
+            Msg::Sound(sound) => Some(sound),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -37,7 +118,7 @@ pub enum GameCommand {
     Menu,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Movement {
     Up,
     Down,
@@ -57,3 +138,382 @@ impl Display for Movement {
         })
     }
 }
+
+impl FromStr for Movement {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Movement, String> {
+        match s.to_lowercase().as_str() {
+            "up" => Ok(Movement::Up),
+            "down" => Ok(Movement::Down),
+            "left" => Ok(Movement::Left),
+            "right" => Ok(Movement::Right),
+            _ => Err(format!("Unknown direction: {}", s)),
+        }
+    }
+}
+
+/// What the player can do, independent of the buttons it is done with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Moves the player, or the menu selection up and down
+    Move(Movement),
+    Pause,
+    /// Picks the selected menu item
+    Confirm,
+    /// Leaves a menu, or opens the menu from the game
+    Back,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match *self {
+            Action::Move(movement) => write!(f, "{}", movement),
+            Action::Pause => write!(f, "Pause"),
+            Action::Confirm => write!(f, "Confirm"),
+            Action::Back => write!(f, "Back"),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Action, String> {
+        match s.to_lowercase().as_str() {
+            "pause" => Ok(Action::Pause),
+            "confirm" => Ok(Action::Confirm),
+            "back" => Ok(Action::Back),
+            _ => s.parse().map(Action::Move).map_err(|_| format!("Unknown action: {}", s)),
+        }
+    }
+}
+
+impl ActionT for Action {
+    fn all() -> Vec<Action> {
+        vec![Action::Move(Movement::Up),
+             Action::Move(Movement::Down),
+             Action::Move(Movement::Left),
+             Action::Move(Movement::Right),
+             Action::Pause,
+             Action::Confirm,
+             Action::Back]
+    }
+
+    /// Arrows and WASD move, P and Pause pause, Return confirms and Escape goes back; on
+    /// controllers the D-pad and left stick move, Start pauses, A confirms and B goes back
+    fn default_bindings() -> Vec<(Input, Action)> {
+        vec![(Input::Key(Keycode::Up), Action::Move(Movement::Up)),
+             (Input::Key(Keycode::W), Action::Move(Movement::Up)),
+             (Input::Key(Keycode::Down), Action::Move(Movement::Down)),
+             (Input::Key(Keycode::S), Action::Move(Movement::Down)),
+             (Input::Key(Keycode::Left), Action::Move(Movement::Left)),
+             (Input::Key(Keycode::A), Action::Move(Movement::Left)),
+             (Input::Key(Keycode::Right), Action::Move(Movement::Right)),
+             (Input::Key(Keycode::D), Action::Move(Movement::Right)),
+             (Input::Key(Keycode::P), Action::Pause),
+             (Input::Key(Keycode::Pause), Action::Pause),
+             (Input::Key(Keycode::Return), Action::Confirm),
+             (Input::Key(Keycode::KpEnter), Action::Confirm),
+             (Input::Key(Keycode::Escape), Action::Back),
+             (Input::Button(Button::DPadUp), Action::Move(Movement::Up)),
+             (Input::Axis(Axis::LeftY, false), Action::Move(Movement::Up)),
+             (Input::Button(Button::DPadDown), Action::Move(Movement::Down)),
+             (Input::Axis(Axis::LeftY, true), Action::Move(Movement::Down)),
+             (Input::Button(Button::DPadLeft), Action::Move(Movement::Left)),
+             (Input::Axis(Axis::LeftX, false), Action::Move(Movement::Left)),
+             (Input::Button(Button::DPadRight), Action::Move(Movement::Right)),
+             (Input::Axis(Axis::LeftX, true), Action::Move(Movement::Right)),
+             (Input::Button(Button::Start), Action::Pause),
+             (Input::Button(Button::A), Action::Confirm),
+             (Input::Button(Button::B), Action::Back),
+             (Input::Button(Button::Back), Action::Back)]
+    }
+}
+
+/// Sound effects, made by `game::sounds`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sound {
+    /// An edible with `nutrition` was eaten, the player grew to `size`
+    Eat { nutrition: f32, size: f32 },
+    /// A spike hit the player, who is now of `size`
+    Hit { size: f32 },
+    Win,
+    Lose,
+    MenuMove,
+    MenuSelect,
+}
+
+/// Parses the messages that can be typed in the console, e.g. `tick 10`, `press Left`,
+/// `command start up`, `pop 1`, `push options` or `action press confirm`. States are named
+/// as they are registered, inputs as in the options file (e.g. `button a`).
+impl FromStr for Msg {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Msg, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let number = |i: usize| -> ::std::result::Result<u32, String> {
+            words.get(i)
+                .and_then(|w| w.parse::<u32>().ok())
+                .ok_or_else(|| format!("Expected a number: {}", s))
+        };
+        let key = |i: usize| -> ::std::result::Result<Keycode, String> {
+            words.get(i)
+                .and_then(|w| Keycode::from_name(w))
+                .ok_or_else(|| format!("Expected a key name: {}", s))
+        };
+        let movement = |i: usize| -> ::std::result::Result<Movement, String> {
+            words.get(i).map_or(Err(format!("Expected a direction: {}", s)), |w| w.parse())
+        };
+        // only registered names can be referred to for as long as a `StackCommand` lives
+        let state = |i: usize| -> ::std::result::Result<&'static str, String> {
+            let name = words.get(i).ok_or_else(|| format!("Expected a state: {}", s))?;
+            STATE_NAMES.iter()
+                .find(|state| *state == name)
+                .cloned()
+                .ok_or_else(|| format!("Unknown state: {}", name))
+        };
+        let action = |i: usize| -> ::std::result::Result<Action, String> {
+            words.get(i).map_or(Err(format!("Expected an action: {}", s)), |w| w.parse())
+        };
+        // the rest of the words, e.g. `button a` or `axis lefty -`
+        let input = |i: usize| -> ::std::result::Result<Input, String> {
+            if words.len() <= i {
+                return Err(format!("Expected an input: {}", s));
+            }
+            words[i..].join(" ").parse()
+        };
+        // whether the second word is press or release
+        let pressed = || -> ::std::result::Result<bool, String> {
+            match words.get(1).map(|w| w.to_lowercase()) {
+                Some(ref w) if w == "press" => Ok(true),
+                Some(ref w) if w == "release" => Ok(false),
+                _ => Err(format!("Expected press or release: {}", s)),
+            }
+        };
+        match words.get(0).map(|w| w.to_lowercase()) {
+            Some(ref w) if w == "noop" => Ok(Msg::NoOp),
+            Some(ref w) if w == "exit" => Ok(Msg::Exit),
+            Some(ref w) if w == "tick" => number(1).map(Msg::Tick),
+            Some(ref w) if w == "press" => key(1).map(Msg::ButtonPressed),
+            Some(ref w) if w == "release" => key(1).map(Msg::ButtonReleased),
+            Some(ref w) if w == "pop" => {
+                number(1).map(|n| Msg::Stack(StackCommand::Pop(n as usize)))
+            }
+            Some(ref w) if w == "push" => state(1).map(|n| Msg::Stack(StackCommand::Push(n))),
+            Some(ref w) if w == "replace" => {
+                state(1).map(|n| Msg::Stack(StackCommand::Replace(n)))
+            }
+            Some(ref w) if w == "clearpush" => {
+                state(1).map(|n| Msg::Stack(StackCommand::ClearAndPush(n)))
+            }
+            Some(ref w) if w == "popto" => state(1).map(|n| Msg::Stack(StackCommand::PopTo(n))),
+            Some(ref w) if w == "action" => {
+                let action = action(2)?;
+                if pressed()? {
+                    Ok(Msg::ActionPressed(action))
+                } else {
+                    Ok(Msg::ActionReleased(action))
+                }
+            }
+            Some(ref w) if w == "controller" => {
+                let input = input(2)?;
+                if pressed()? {
+                    Ok(Msg::ControllerPressed(input))
+                } else {
+                    Ok(Msg::ControllerReleased(input))
+                }
+            }
+            Some(ref w) if w == "options" => {
+                match words.get(1).map(|w| w.to_lowercase()) {
+                    Some(ref w) if w == "select" => movement(2).map(Msg::OptionsSelect),
+                    Some(ref w) if w == "set" => input(2).map(Msg::OptionsSet),
+                    Some(ref w) if w == "reset" => Ok(Msg::OptionsReset),
+                    Some(ref w) if w == "changed" => Ok(Msg::OptionsChanged),
+                    _ => Err(format!("Expected select, set, reset or changed: {}", s)),
+                }
+            }
+            Some(ref w) if w == "focus" => {
+                match words.get(1).map(|w| w.to_lowercase()) {
+                    Some(ref w) if w == "lost" => Ok(Msg::FocusLost),
+                    Some(ref w) if w == "gained" => Ok(Msg::FocusGained),
+                    _ => Err(format!("Expected lost or gained: {}", s)),
+                }
+            }
+            Some(ref w) if w == "display" => {
+                match words.get(1).map(|w| w.to_lowercase()) {
+                    Some(ref w) if w == "fullscreen" => {
+                        Ok(Msg::Display(DisplayCommand::ToggleFullscreen))
+                    }
+                    Some(ref w) if w == "mode" => {
+                        Ok(Msg::Display(DisplayCommand::NextFullscreenMode))
+                    }
+                    Some(ref w) if w == "scaling" => Ok(Msg::Display(DisplayCommand::NextScaling)),
+                    Some(ref w) if w == "autopause" => {
+                        Ok(Msg::Display(DisplayCommand::TogglePauseOnFocusLoss))
+                    }
+                    _ => Err(format!("Expected fullscreen, mode, scaling or autopause: {}", s)),
+                }
+            }
+            Some(ref w) if w == "sound" => {
+                // sizes are made up when left out, so sounds can be tried quickly
+                let size = |i: usize| number(i).map(|n| n as f32).unwrap_or(20.0);
+                let sound = match words.get(1).map(|w| w.to_lowercase()) {
+                    Some(ref w) if w == "eat" => {
+                        Sound::Eat {
+                            nutrition: size(2),
+                            size: size(3),
+                        }
+                    }
+                    Some(ref w) if w == "hit" => Sound::Hit { size: size(2) },
+                    Some(ref w) if w == "win" => Sound::Win,
+                    Some(ref w) if w == "lose" => Sound::Lose,
+                    Some(ref w) if w == "move" => Sound::MenuMove,
+                    Some(ref w) if w == "select" => Sound::MenuSelect,
+                    _ => return Err(format!("Expected eat, hit, win, lose, move or select: {}", s)),
+                };
+                Ok(Msg::Sound(sound))
+            }
+            Some(ref w) if w == "audio" => {
+                match words.get(1).map(|w| w.to_lowercase()) {
+                    Some(ref w) if w == "up" => Ok(Msg::Audio(AudioCommand::VolumeUp)),
+                    Some(ref w) if w == "down" => Ok(Msg::Audio(AudioCommand::VolumeDown)),
+                    Some(ref w) if w == "mute" => Ok(Msg::Audio(AudioCommand::ToggleMute)),
+                    _ => Err(format!("Expected up, down or mute: {}", s)),
+                }
+            }
+            Some(ref w) if w == "command" => {
+                let command = match words.get(1).map(|w| w.to_lowercase()) {
+                    Some(ref w) if w == "start" => GameCommand::StartMovement(movement(2)?),
+                    Some(ref w) if w == "stop" => GameCommand::StopMovement(movement(2)?),
+                    Some(ref w) if w == "pause" => GameCommand::Pause,
+                    Some(ref w) if w == "resume" => GameCommand::Resume,
+                    Some(ref w) if w == "menu" => GameCommand::Menu,
+                    _ => return Err(format!("Expected start, stop, pause, resume or menu: {}", s)),
+                };
+                Ok(Msg::Command(command))
+            }
+            _ => Err(format!("Unknown message: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `Msg` can not be compared, its `Debug` output can
+    fn parsed(s: &str) -> String {
+        match s.parse::<Msg>() {
+            Ok(msg) => format!("{:?}", msg),
+            Err(e) => panic!("{} was refused: {}", s, e),
+        }
+    }
+
+    fn assert_parses(s: &str, msg: Msg) {
+        assert_eq!(parsed(s), format!("{:?}", msg), "parsing {}", s);
+    }
+
+    #[test]
+    fn simple_messages_are_parsed() {
+        assert_parses("noop", Msg::NoOp);
+        assert_parses("EXIT", Msg::Exit);
+        assert_parses("tick 10", Msg::Tick(10));
+        assert_parses("press Left", Msg::ButtonPressed(Keycode::Left));
+        assert_parses("release Space", Msg::ButtonReleased(Keycode::Space));
+        assert_parses("focus lost", Msg::FocusLost);
+        assert_parses("focus gained", Msg::FocusGained);
+        assert_parses("display fullscreen", Msg::Display(DisplayCommand::ToggleFullscreen));
+        assert_parses("display mode", Msg::Display(DisplayCommand::NextFullscreenMode));
+        assert_parses("display scaling", Msg::Display(DisplayCommand::NextScaling));
+        assert_parses("display autopause",
+                      Msg::Display(DisplayCommand::TogglePauseOnFocusLoss));
+        assert_parses("audio up", Msg::Audio(AudioCommand::VolumeUp));
+        assert_parses("audio down", Msg::Audio(AudioCommand::VolumeDown));
+        assert_parses("audio mute", Msg::Audio(AudioCommand::ToggleMute));
+    }
+
+    #[test]
+    fn sounds_are_parsed_with_made_up_sizes() {
+        assert_parses("sound eat 5 30",
+                      Msg::Sound(Sound::Eat {
+                          nutrition: 5.0,
+                          size: 30.0,
+                      }));
+        assert_parses("sound hit", Msg::Sound(Sound::Hit { size: 20.0 }));
+        assert_parses("sound win", Msg::Sound(Sound::Win));
+        assert_parses("sound lose", Msg::Sound(Sound::Lose));
+        assert_parses("sound move", Msg::Sound(Sound::MenuMove));
+        assert_parses("sound select", Msg::Sound(Sound::MenuSelect));
+    }
+
+    #[test]
+    fn game_commands_are_parsed() {
+        assert_parses("command start up", Msg::Command(GameCommand::StartMovement(Movement::Up)));
+        assert_parses("command stop left",
+                      Msg::Command(GameCommand::StopMovement(Movement::Left)));
+        assert_parses("command pause", Msg::Command(GameCommand::Pause));
+        assert_parses("command resume", Msg::Command(GameCommand::Resume));
+        assert_parses("command menu", Msg::Command(GameCommand::Menu));
+    }
+
+    #[test]
+    fn stack_commands_name_registered_states() {
+        assert_parses("pop 2", Msg::Stack(StackCommand::Pop(2)));
+        assert_parses("push options", Msg::Stack(StackCommand::Push("options")));
+        assert_parses("replace game_menu", Msg::Stack(StackCommand::Replace("game_menu")));
+        assert_parses("clearpush intro", Msg::Stack(StackCommand::ClearAndPush("intro")));
+        assert_parses("popto main_menu", Msg::Stack(StackCommand::PopTo("main_menu")));
+    }
+
+    #[test]
+    fn actions_and_controller_inputs_are_parsed() {
+        assert_parses("action press confirm", Msg::ActionPressed(Action::Confirm));
+        assert_parses("action release down",
+                      Msg::ActionReleased(Action::Move(Movement::Down)));
+        assert_parses("controller press button a",
+                      Msg::ControllerPressed(Input::Button(Button::A)));
+        assert_parses("controller release axis lefty -",
+                      Msg::ControllerReleased(Input::Axis(Axis::LeftY, false)));
+    }
+
+    #[test]
+    fn options_messages_are_parsed() {
+        assert_parses("options select right", Msg::OptionsSelect(Movement::Right));
+        assert_parses("options set key I", Msg::OptionsSet(Input::Key(Keycode::I)));
+        assert_parses("options reset", Msg::OptionsReset);
+        assert_parses("options changed", Msg::OptionsChanged);
+    }
+
+    #[test]
+    fn mistakes_are_explained() {
+        let errors = [("", "Unknown message: "),
+                      ("jump", "Unknown message: jump"),
+                      ("tick", "Expected a number: tick"),
+                      ("press Nope", "Expected a key name: press Nope"),
+                      ("pop many", "Expected a number: pop many"),
+                      ("push", "Expected a state: push"),
+                      ("push Options", "Unknown state: Options"),
+                      ("action press", "Expected an action: action press"),
+                      ("action press jump", "Unknown action: jump"),
+                      ("action hold up", "Expected press or release: action hold up"),
+                      ("controller press", "Expected an input: controller press"),
+                      ("controller press button z", "Unknown input: button z"),
+                      ("options", "Expected select, set, reset or changed: options"),
+                      ("options select", "Expected a direction: options select"),
+                      ("focus", "Expected lost or gained: focus"),
+                      ("display zoom",
+                       "Expected fullscreen, mode, scaling or autopause: display zoom"),
+                      ("sound boom", "Expected eat, hit, win, lose, move or select: sound boom"),
+                      ("audio loud", "Expected up, down or mute: audio loud"),
+                      ("command jump",
+                       "Expected start, stop, pause, resume or menu: command jump"),
+                      ("command start north", "Unknown direction: north")];
+        for &(text, error) in errors.iter() {
+            match text.parse::<Msg>() {
+                Err(e) => assert_eq!(e, error),
+                Ok(msg) => panic!("{} was parsed as {:?}", text, msg),
+            }
+        }
+    }
+}
@@ -1,22 +1,169 @@
+use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
-
-/// DEBUG const indicates whether we should look for resources and textures inside the
-/// current dir subdirs or we should go up (since we're inside the debug build in repo)
-#[cfg(debug_assertions)]
-const DEBUG: bool = true;
-#[cfg(not(debug_assertions))]
-const DEBUG: bool = false;
-
-/// Returns a `PathBuf` for given resource, assembling it in accordance to DEBUG
-pub fn get_resource_path(rname: &str) -> PathBuf {
-    let exe = env::current_exe().unwrap();
-    let exe_dir = exe.parent().unwrap();
-    let resource_root = if DEBUG {
-        exe_dir.parent().unwrap().parent().unwrap().join("resources")
-    } else {
-        exe_dir.join("resources")
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use engine::error::EngineError;
+use log::Category;
+
+/// Environment variable naming a directory that is searched for resources before any other
+pub const RESOURCES_ENV: &'static str = "HUNGRY_PIXEL_RESOURCES";
+
+/// Resources built into the binary, used when they are not found in any search path
+const EMBEDDED: [(&'static str, &'static [u8]); 1] =
+    [("PressStart2P-Regular.ttf", include_bytes!("../resources/PressStart2P-Regular.ttf"))];
+
+/// Resource manager
+/// Finds game assets (fonts, images, sounds) by file name in a list of directories, falling
+/// back to the copies embedded in the binary, and keeps the loaded contents by name.
+pub struct Resources {
+    search_paths: Vec<PathBuf>,
+    cache: HashMap<String, Rc<Vec<u8>>>,
+}
+
+impl Resources {
+    /// Searches, in order: the directory in `$HUNGRY_PIXEL_RESOURCES`, the data dir
+    /// (`$XDG_DATA_HOME/hungry-pixel/resources`), `resources` next to the executable and
+    /// `resources` in the repository the game was built from
+    pub fn new() -> Resources {
+        let mut search_paths = Vec::new();
+        if let Some(dir) = env::var_os(RESOURCES_ENV) {
+            search_paths.push(PathBuf::from(dir));
+        }
+        search_paths.push(get_data_path("resources"));
+        if let Ok(exe) = env::current_exe() {
+            if let Some(exe_dir) = exe.parent() {
+                search_paths.push(exe_dir.join("resources"));
+            }
+        }
+        search_paths.push(Path::new(env!("CARGO_MANIFEST_DIR")).join("resources"));
+        Resources::with_search_paths(search_paths)
+    }
+
+    pub fn with_search_paths(search_paths: Vec<PathBuf>) -> Resources {
+        Resources {
+            search_paths: search_paths,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// Path of the first file called `name` in the search paths
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        self.candidates(name).into_iter().find(|path| path.is_file())
+    }
+
+    /// Every path the resource called `name` may be found at, in the search order
+    pub fn candidates(&self, name: &str) -> Vec<PathBuf> {
+        self.search_paths.iter().map(|dir| dir.join(name)).collect()
+    }
+
+    /// Drops the cached contents of `name`, so that the next `load` reads it again
+    pub fn forget(&mut self, name: &str) {
+        self.cache.remove(name);
+    }
+
+    /// Contents of the resource called `name`, read on first use and cached afterwards. A
+    /// file that can not be read is logged and skipped, in favour of the later search paths
+    /// and the embedded copy.
+    pub fn load(&mut self, name: &str) -> Result<Rc<Vec<u8>>, EngineError> {
+        if let Some(data) = self.cache.get(name) {
+            return Ok(data.clone());
+        }
+        let data = match self.read(name) {
+            Some(data) => data,
+            None => {
+                match EMBEDDED.iter().find(|&&(embedded, _)| embedded == name) {
+                    Some(&(_, bytes)) => {
+                        info!(Category::Engine, "Using the embedded copy of {}", name);
+                        bytes.to_vec()
+                    }
+                    None => {
+                        return Err(EngineError::MissingResource(name.to_string(),
+                                                                self.search_paths.clone()))
+                    }
+                }
+            }
+        };
+        let data = Rc::new(data);
+        self.cache.insert(name.to_string(), data.clone());
+        Ok(data)
+    }
+
+    /// Contents of the first file called `name` in the search paths that can be read
+    fn read(&self, name: &str) -> Option<Vec<u8>> {
+        for path in self.candidates(name).into_iter().filter(|path| path.is_file()) {
+            debug!(Category::Engine, "Loading {} from {}", name, path.display());
+            let mut data = Vec::new();
+            match File::open(&path).and_then(|mut file| file.read_to_end(&mut data)) {
+                Ok(_) => return Some(data),
+                Err(e) => warn!(Category::Engine, "Could not read {}: {}", path.display(), e),
+            }
+        }
+        None
+    }
+}
+
+/// Returns a `PathBuf` for a file written by the game (e.g. logs), inside
+/// `$XDG_DATA_HOME` or `~/.local/share`, falling back to the current dir
+pub fn get_data_path(name: &str) -> PathBuf {
+    let data_root = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            match env::var_os("HOME") {
+                Some(home) => PathBuf::from(home).join(".local").join("share"),
+                None => PathBuf::from("."),
+            }
+        }
+    };
+
+    data_root.join("hungry-pixel").join(name)
+}
+
+/// Returns a `PathBuf` for a file holding user settings, inside `$XDG_CONFIG_HOME` or
+/// `~/.config`, falling back to the current dir
+pub fn get_config_path(name: &str) -> PathBuf {
+    let config_root = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            match env::var_os("HOME") {
+                Some(home) => PathBuf::from(home).join(".config"),
+                None => PathBuf::from("."),
+            }
+        }
     };
 
-    resource_root.join(rname)
+    config_root.join("hungry-pixel").join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    use super::Resources;
+
+    #[test]
+    fn later_paths_and_embedded_copies_are_used() {
+        let root = env::temp_dir().join(format!("hungry-pixel-resources-{}",
+                                                ::std::process::id()));
+        let (first, second) = (root.join("first"), root.join("second"));
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        // not a file, so it is skipped
+        fs::create_dir_all(first.join("sprites.txt")).unwrap();
+        File::create(second.join("sprites.txt")).unwrap().write_all(b"player").unwrap();
+
+        let mut resources = Resources::with_search_paths(vec![first, second]);
+        assert_eq!(*resources.load("sprites.txt").unwrap(), b"player".to_vec());
+        assert!(!resources.load("PressStart2P-Regular.ttf").unwrap().is_empty());
+        assert!(resources.load("missing.png").is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
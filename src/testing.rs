@@ -0,0 +1,20 @@
+//! Helpers shared by the unit tests
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Path in the temporary directory, unique to `name` and to this test run
+pub fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("hungry-pixel-{}-{}", name, ::std::process::id()))
+}
+
+/// Calls `f` with the path of a temporary file holding `text`, the file is removed afterwards
+pub fn with_text_file<T, F: FnOnce(&Path) -> T>(name: &str, text: &str, f: F) -> T {
+    let path = temp_path(name);
+    File::create(&path).and_then(|mut file| file.write_all(text.as_bytes())).unwrap();
+    let result = f(&path);
+    fs::remove_file(&path).unwrap();
+    result
+}